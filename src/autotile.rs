@@ -0,0 +1,219 @@
+use crate::color::Color;
+use crate::game::{Cells, Coords, Game, Phase};
+use crate::mask::FAR_MASK;
+
+/// Number of tiles in a tileset.
+pub const TILE_COUNT: u32 = 14;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Side {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Corner {
+    NorthWest,
+    NorthEast,
+    SouthEast,
+    SouthWest,
+}
+
+/// Piece of terrain picked for a cell from which of its neighbours are the same material.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Tile {
+    /// Surrounded on all eight sides.
+    Interior,
+    /// Exposed on one side.
+    Edge(Side),
+    /// Exposed on the two sides meeting at the corner.
+    OuterCorner(Corner),
+    /// Connected on every side, exposed only diagonally at the corner.
+    InnerCorner(Corner),
+    /// Exposed on two opposite sides or on three or more, drawn with every side as an edge.
+    Single,
+}
+
+impl Tile {
+    /// Picks the tile from the neighbours in `FAR_MASK` order, true where the neighbour is connected.
+    pub fn from_neighbours(neighbours: [bool; 8]) -> Tile {
+        let [north_west, north, north_east, west, east, south_west, south, south_east] = neighbours;
+        match (north, east, south, west) {
+            (true, true, true, true) => match (north_west, north_east, south_east, south_west) {
+                (false, _, _, _) => Tile::InnerCorner(Corner::NorthWest),
+                (_, false, _, _) => Tile::InnerCorner(Corner::NorthEast),
+                (_, _, false, _) => Tile::InnerCorner(Corner::SouthEast),
+                (_, _, _, false) => Tile::InnerCorner(Corner::SouthWest),
+                _ => Tile::Interior,
+            },
+            (false, true, true, true) => Tile::Edge(Side::North),
+            (true, false, true, true) => Tile::Edge(Side::East),
+            (true, true, false, true) => Tile::Edge(Side::South),
+            (true, true, true, false) => Tile::Edge(Side::West),
+            (false, true, true, false) => Tile::OuterCorner(Corner::NorthWest),
+            (false, false, true, true) => Tile::OuterCorner(Corner::NorthEast),
+            (true, false, false, true) => Tile::OuterCorner(Corner::SouthEast),
+            (true, true, false, false) => Tile::OuterCorner(Corner::SouthWest),
+            _ => Tile::Single,
+        }
+    }
+
+    /// Picks the tile of the cell, the world border counts as connected.
+    pub fn of(game: &Game, x: i32, y: i32) -> Tile {
+        let kind = game.get_cell(x, y).kind;
+        let mut mask = FAR_MASK;
+        mask.coords = Coords { x, y };
+        let mut neighbours = [false; 8];
+        for (neighbour, coords) in neighbours.iter_mut().zip(mask.iter_coords()) {
+            *neighbour = game.try_get_cell(coords.x, coords.y).is_none_or(|cell| cell.kind == kind);
+        }
+        Tile::from_neighbours(neighbours)
+    }
+
+    /// Returns the position of the tile in a tileset.
+    pub fn index(&self) -> u32 {
+        match self {
+            Tile::Interior => 0,
+            Tile::Edge(side) => 1 + *side as u32,
+            Tile::OuterCorner(corner) => 5 + *corner as u32,
+            Tile::InnerCorner(corner) => 9 + *corner as u32,
+            Tile::Single => 13,
+        }
+    }
+
+    /// Returns whether drawing the tile paints the side as exposed.
+    fn is_exposed(&self, side: Side) -> bool {
+        let corner_sides = |corner: Corner| match corner {
+            Corner::NorthWest => [Side::North, Side::West],
+            Corner::NorthEast => [Side::North, Side::East],
+            Corner::SouthEast => [Side::South, Side::East],
+            Corner::SouthWest => [Side::South, Side::West],
+        };
+        match *self {
+            Tile::Interior | Tile::InnerCorner(_) => false,
+            Tile::Edge(exposed) => exposed == side,
+            Tile::OuterCorner(corner) => corner_sides(corner).contains(&side),
+            Tile::Single => true,
+        }
+    }
+}
+
+impl Cells {
+    /// Returns whether the material is drawn with tiles when the renderer has a tileset.
+    pub fn is_autotiled(&self) -> bool {
+        self.phase() == Phase::Solid
+    }
+}
+
+/// Square tiles painted over the colour of autotiled cells, in the order of `Tile::index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tileset {
+    size: u32,
+    /// Tiles next to each other in one row.
+    pixels: Vec<Color>,
+}
+
+impl Tileset {
+    /// Builds a tileset from a row of `TILE_COUNT` tiles of `size` by `size` pixels.
+    pub fn new(size: u32, pixels: Vec<Color>) -> Option<Tileset> {
+        (size > 0 && pixels.len() == (size * size * TILE_COUNT) as usize).then_some(Tileset { size, pixels })
+    }
+
+    /// Builds bevelled tiles, lit from the top left, that work with any material colour.
+    pub fn generated(size: u32) -> Tileset {
+        let size = size.max(1);
+        let border = (size / 8).max(1);
+        let light = Color::rgba(255, 255, 255, 96);
+        let shadow = Color::rgba(0, 0, 0, 96);
+        let mut pixels = vec![Color::TRANSPARENT; (size * size * TILE_COUNT) as usize];
+        let tiles = [Side::North, Side::East, Side::South, Side::West]
+            .map(Tile::Edge)
+            .into_iter()
+            .chain([Corner::NorthWest, Corner::NorthEast, Corner::SouthEast, Corner::SouthWest].map(Tile::OuterCorner))
+            .chain([Corner::NorthWest, Corner::NorthEast, Corner::SouthEast, Corner::SouthWest].map(Tile::InnerCorner))
+            .chain([Tile::Single]);
+        for tile in tiles {
+            for y in 0..size {
+                for x in 0..size {
+                    let (left, top) = (x < border, y < border);
+                    let (right, bottom) = (x >= size - border, y >= size - border);
+                    let color = match tile {
+                        Tile::InnerCorner(Corner::NorthWest) if left && top => shadow,
+                        Tile::InnerCorner(Corner::NorthEast) if right && top => shadow,
+                        Tile::InnerCorner(Corner::SouthEast) if right && bottom => light,
+                        Tile::InnerCorner(Corner::SouthWest) if left && bottom => light,
+                        _ if (top && tile.is_exposed(Side::North)) || (left && tile.is_exposed(Side::West)) => light,
+                        _ if (bottom && tile.is_exposed(Side::South)) || (right && tile.is_exposed(Side::East)) => shadow,
+                        _ => continue,
+                    };
+                    pixels[(y * size * TILE_COUNT + tile.index() * size + x) as usize] = color;
+                }
+            }
+        }
+        Tileset { size, pixels }
+    }
+
+    /// Width and height of a tile in pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the colour of the tile's pixel painted over the material colour.
+    pub fn pixel(&self, tile: Tile, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.size * TILE_COUNT + tile.index() * self.size + x) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Cell;
+
+    /// Reads the neighbours from a 3 by 3 picture, `#` where connected, the middle is ignored.
+    fn neighbours(rows: [&str; 3]) -> [bool; 8] {
+        let cells: Vec<bool> = rows.concat().chars().map(|c| c == '#').collect();
+        [cells[0], cells[1], cells[2], cells[3], cells[5], cells[6], cells[7], cells[8]]
+    }
+
+    #[test]
+    fn from_neighbours_picks_every_tile() {
+        let table = [
+            (["###", "###", "###"], Tile::Interior),
+            (["...", "###", "###"], Tile::Edge(Side::North)),
+            (["##.", "##.", "##."], Tile::Edge(Side::East)),
+            (["###", "###", "..."], Tile::Edge(Side::South)),
+            ([".##", ".##", ".##"], Tile::Edge(Side::West)),
+            // the diagonals only matter when all four sides are connected
+            (["#.#", "###", "###"], Tile::Edge(Side::North)),
+            (["...", ".##", ".##"], Tile::OuterCorner(Corner::NorthWest)),
+            (["...", "##.", "##."], Tile::OuterCorner(Corner::NorthEast)),
+            (["##.", "##.", "..."], Tile::OuterCorner(Corner::SouthEast)),
+            ([".##", ".##", "..."], Tile::OuterCorner(Corner::SouthWest)),
+            ([".##", "###", "###"], Tile::InnerCorner(Corner::NorthWest)),
+            (["##.", "###", "###"], Tile::InnerCorner(Corner::NorthEast)),
+            (["###", "###", "##."], Tile::InnerCorner(Corner::SouthEast)),
+            (["###", "###", ".##"], Tile::InnerCorner(Corner::SouthWest)),
+            (["...", "...", "..."], Tile::Single),
+            ([".#.", ".#.", ".#."], Tile::Single),
+            (["...", "###", "..."], Tile::Single),
+            ([".#.", "...", "..."], Tile::Single),
+        ];
+        for (rows, tile) in table {
+            assert_eq!(Tile::from_neighbours(neighbours(rows)), tile, "{:?}", rows);
+        }
+    }
+
+    #[test]
+    fn world_border_counts_as_connected() {
+        let mut game = Game::with_seed(3, 1, 0);
+        game.set_cell(0, 0, Cell::new(Cells::Wall));
+        game.set_cell(2, 0, Cell::new(Cells::Wall));
+        assert_eq!(Tile::of(&game, 0, 0), Tile::Edge(Side::East));
+        assert_eq!(Tile::of(&game, 2, 0), Tile::Edge(Side::West));
+        // the air between the walls is only connected through the border, above and below it
+        assert_eq!(Tile::of(&game, 1, 0), Tile::Single);
+        assert_eq!(Tile::of(&Game::with_seed(1, 1, 0), 0, 0), Tile::Interior);
+    }
+}
@@ -0,0 +1,209 @@
+//! Runs the simulation without a window, for batch experiments and regression checks.
+//!
+//! ```text
+//! jiskricka-headless <world> [--ticks N] [--seed S] [--until-stable K] [--tui] [--out <world>]
+//!                     [--capture <path>] [--capture-every N] [--capture-scale N]
+//! ```
+//!
+//! Worlds ending in `.txt` use the text format, `.png` the image format, anything else the save format.
+//! With `--tui` the world is shown in the terminal and runs until the user quits.
+//! `--capture` writes every Nth tick to an animated gif, or to a directory of png files for other paths.
+
+mod tui;
+
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+
+use jiskricka::capture::Capture;
+use jiskricka::game::{Frame, Game};
+use jiskricka::image::Palette;
+use jiskricka::text::Legend;
+
+const USAGE: &str = "usage: jiskricka-headless <world> [--ticks N] [--seed S] [--until-stable K] [--tui] [--out <world>] [--capture <path>] [--capture-every N] [--capture-scale N]";
+
+struct Options {
+    input: String,
+    output: Option<String>,
+    ticks: u64,
+    seed: Option<u64>,
+    /// Stop once no cell changed for this many ticks.
+    until_stable: Option<u64>,
+    /// Run interactively in the terminal instead of for a number of ticks.
+    tui: bool,
+    capture: Option<String>,
+    capture_every: u32,
+    capture_scale: u32,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            input: String::new(),
+            output: None,
+            ticks: 100,
+            seed: None,
+            until_stable: None,
+            tui: false,
+            capture: None,
+            capture_every: 1,
+            capture_scale: 4,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--ticks" => options.ticks = parse_number(value()?)?,
+                "--seed" => options.seed = Some(parse_number(value()?)?),
+                "--until-stable" => options.until_stable = Some(parse_number(value()?)?),
+                "--out" => options.output = Some(value()?.clone()),
+                "--tui" => options.tui = true,
+                "--capture" => options.capture = Some(value()?.clone()),
+                "--capture-every" => options.capture_every = parse_number(value()?)?,
+                "--capture-scale" => options.capture_scale = parse_number(value()?)?,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ if options.input.is_empty() => options.input = arg.clone(),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+        if options.input.is_empty() {
+            return Err(USAGE.to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} is not a valid number", value))
+}
+
+fn extension(path: &str) -> Option<&str> {
+    Path::new(path).extension().and_then(|ext| ext.to_str())
+}
+
+fn load_world(path: &str) -> Result<Game, String> {
+    let result = match extension(path) {
+        Some("txt") => std::fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| Game::from_text(&text, &Legend::default()).map_err(|err| err.to_string())),
+        Some("png") => Game::load_png(path, &Palette::default()).map_err(|err| err.to_string()),
+        _ => Game::load(path).map_err(|err| err.to_string()),
+    };
+    result.map_err(|err| format!("failed to load {}: {}", path, err))
+}
+
+fn save_world(game: &Game, path: &str) -> Result<(), String> {
+    let result = match extension(path) {
+        Some("txt") => std::fs::write(path, game.to_text(&Legend::default())).map_err(|err| err.to_string()),
+        Some("png") => game.save_png(path, 1).map_err(|err| err.to_string()),
+        _ => game.save(path).map_err(|err| err.to_string()),
+    };
+    result.map_err(|err| format!("failed to save {}: {}", path, err))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let mut game = load_world(&options.input)?;
+    if let Some(seed) = options.seed {
+        game.reseed(seed);
+    }
+
+    if options.tui {
+        tui::run(&mut game).map_err(|err| format!("terminal error: {}", err))?;
+        if let Some(output) = &options.output {
+            save_world(&game, output)?;
+        }
+        return Ok(());
+    }
+
+    let mut capture = match &options.capture {
+        Some(path) => Some(
+            Capture::create(path, options.capture_every)
+                .map_err(|err| format!("failed to capture to {}: {}", path, err))?
+                .with_scale(options.capture_scale),
+        ),
+        None => None,
+    };
+    // the first capture error stops capturing, the simulation still finishes
+    let mut capture_result = Ok(());
+    let mut capture_frame = |game: &Game| {
+        if let (Some(capture), true) = (&mut capture, capture_result.is_ok()) {
+            capture_result = capture.capture(game).map(drop);
+        }
+    };
+
+    let start = Instant::now();
+    capture_frame(&game);
+    let settle = match options.until_stable {
+        Some(limit) => Some(game.settle_with(limit, options.ticks, &mut capture_frame)),
+        None => {
+            let mut frame = Frame::new();
+            for _ in 0..options.ticks {
+                game.update(&mut frame);
+                capture_frame(&game);
+            }
+            None
+        }
+    };
+    let elapsed = start.elapsed();
+
+    let captured = match (capture, &options.capture) {
+        (Some(capture), Some(path)) => Some(
+            capture_result
+                .and_then(|()| capture.finish())
+                .map_err(|err| format!("failed to capture to {}: {}", path, err))?,
+        ),
+        _ => None,
+    };
+
+    if let Some(output) = &options.output {
+        save_world(&game, output)?;
+    }
+
+    println!("ticks: {}", settle.map_or(options.ticks, |settle| settle.ticks));
+    println!("final tick: {}", game.tick());
+    println!("seed: {}", game.seed());
+    if let Some(settle) = settle {
+        println!("stable: {}", settle.converged);
+    }
+    println!("elapsed: {:.3}s", elapsed.as_secs_f64());
+    if let Some(captured) = captured {
+        println!("captured frames: {}", captured);
+    }
+    print!("{}", game.stats());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn capture_options_are_parsed() {
+        let options = parse(&["world.txt", "--capture-every", "3", "--capture-scale", "2"]).unwrap();
+        assert_eq!((options.capture_every, options.capture_scale), (3, 2));
+    }
+
+    #[test]
+    fn capture_options_out_of_range_are_errors() {
+        for option in ["--capture-every", "--capture-scale"] {
+            assert_eq!(
+                parse(&["world.txt", option, "4294967296"]).err(),
+                Some("4294967296 is not a valid number".to_string())
+            );
+            assert!(parse(&["world.txt", option, "-1"]).is_err());
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Interactive terminal mode, for watching and poking the simulation over SSH.
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use jiskricka::camera::Area;
+use jiskricka::color::Color;
+use jiskricka::game::{Action, BackgroundAction, Cell, CellAction, Cells, Coords, Frame, Game};
+use jiskricka::light::Lighting;
+use jiskricka::overlay::Overlay;
+use jiskricka::render::Renderer;
+use jiskricka::terminal::TerminalRenderer;
+
+const TICK: Duration = Duration::from_millis(50);
+const CURSOR_COLOR: Color = Color::rgb(255, 0, 255);
+const HELP: &str = "space pause  n step  arrows/wasd move  1-4 material  enter place  b background  l light  F1-F4 overlays  q quit";
+
+/// Keeps the terminal in raw mode on the alternate screen, restoring it when dropped.
+struct RawTerminal {
+    saved: String,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<RawTerminal> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(RawTerminal { saved })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Runs `stty` on the terminal of the process and returns its output.
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the size of the terminal as columns and lines.
+fn terminal_size() -> (u32, u32) {
+    let size = stty(&["size"]).ok().and_then(|size| {
+        let mut numbers = size.split_whitespace().map(|number| number.parse().ok());
+        let lines = numbers.next()??;
+        let columns = numbers.next()??;
+        Some((columns, lines))
+    });
+    size.unwrap_or((80, 24))
+}
+
+/// Reads stdin on its own thread so the simulation keeps running between key presses.
+fn spawn_input() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 32];
+        while let Ok(read @ 1..) = stdin.read(&mut buffer) {
+            if sender.send(buffer[..read].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Key {
+    Char(u8),
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Function key, starting at 0 for F1.
+    Function(u8),
+}
+
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i..] {
+            [0x1b, b'[', arrow @ b'A'..=b'D', ..] => {
+                keys.push(match arrow {
+                    b'A' => Key::Up,
+                    b'B' => Key::Down,
+                    b'C' => Key::Right,
+                    _ => Key::Left,
+                });
+                i += 3;
+            }
+            [0x1b, b'O', key @ b'P'..=b'S', ..] => {
+                keys.push(Key::Function(key - b'P'));
+                i += 3;
+            }
+            [byte, ..] => {
+                keys.push(Key::Char(byte));
+                i += 1;
+            }
+            [] => break,
+        }
+    }
+    keys
+}
+
+struct Tui {
+    paused: bool,
+    cursor: Coords,
+    material: Cells,
+    /// Top left cell of the view.
+    view: Coords,
+}
+
+impl Tui {
+    /// Handles a key press, returns `false` when the user asked to quit.
+    fn handle(&mut self, game: &mut Game, frame: &mut Frame, renderer: &mut TerminalRenderer, key: Key) -> bool {
+        let (dx, dy) = match key {
+            Key::Up | Key::Char(b'w') => (0, -1),
+            Key::Down | Key::Char(b's') => (0, 1),
+            Key::Left | Key::Char(b'a') => (-1, 0),
+            Key::Right | Key::Char(b'd') => (1, 0),
+            _ => (0, 0),
+        };
+        // empty worlds have no cell to move the cursor to
+        if game.width() > 0 && game.height() > 0 {
+            self.cursor = Coords {
+                x: (self.cursor.x + dx).clamp(0, game.width() as i32 - 1),
+                y: (self.cursor.y + dy).clamp(0, game.height() as i32 - 1),
+            };
+        }
+        match key {
+            // ctrl+c doesn't raise a signal in raw mode
+            Key::Char(b'q' | 0x03) => return false,
+            Key::Char(b' ') => self.paused = !self.paused,
+            Key::Char(b'n') => game.update(frame),
+            Key::Char(b'l') => {
+                game.lighting = match game.lighting {
+                    Some(_) => None,
+                    None => Some(Lighting::default()),
+                };
+            }
+            Key::Char(digit @ b'1'..=b'9') => {
+                if let Some(material) = Cells::from_index((digit - b'1') as usize) {
+                    self.material = material;
+                }
+            }
+            Key::Char(b'\r' | b'\n' | b'p') => {
+                frame.add_action(Action::Cell(self.cursor, CellAction::Set(Cell::new(self.material))));
+                game.apply_frame(frame);
+            }
+            Key::Char(b'b') => {
+                frame.add_action(Action::Background(self.cursor, BackgroundAction::Set(self.material)));
+                game.apply_frame(frame);
+            }
+            Key::Function(index) => {
+                if let Some(overlay) = Overlay::ALL.get(index as usize) {
+                    renderer.overlays.toggle(*overlay);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Scrolls the view so the cursor stays visible and returns the area to draw.
+    fn follow(&mut self, width: u32, height: u32) -> Area {
+        let (width, height) = (width.max(1), height.max(1));
+        self.view.x = self.view.x.clamp(self.cursor.x - width as i32 + 1, self.cursor.x).max(0);
+        self.view.y = self.view.y.clamp(self.cursor.y - height as i32 + 1, self.cursor.y).max(0);
+        Area { x: self.view.x, y: self.view.y, width, height }
+    }
+
+    fn draw(&mut self, game: &Game, renderer: &mut TerminalRenderer, out: &mut impl Write) -> io::Result<()> {
+        let (columns, lines) = terminal_size();
+        // two lines are left for the status and the help
+        let area = self.follow(columns, lines.saturating_sub(2) * 2);
+        game.draw(renderer, area);
+        renderer.draw_cell(self.cursor.x, self.cursor.y, CURSOR_COLOR);
+
+        let mut screen = String::from("\x1b[H");
+        screen.push_str(&renderer.to_ansi("\x1b[K\r\n"));
+        let stats = game.stats();
+        screen.push_str(&format!(
+            "tick {}{}  material {:?}  cursor {},{}  {}\x1b[K\r\n",
+            game.tick(),
+            if self.paused { " (paused)" } else { "" },
+            self.material,
+            self.cursor.x,
+            self.cursor.y,
+            Cells::ALL.iter().map(|kind| format!("{:?} {}", kind, stats.count(*kind))).collect::<Vec<_>>().join("  "),
+        ));
+        screen.push_str(HELP);
+        screen.push_str("\x1b[K\x1b[J");
+        out.write_all(screen.as_bytes())?;
+        out.flush()
+    }
+}
+
+/// Runs the simulation in the terminal until the user quits.
+pub fn run(game: &mut Game) -> io::Result<()> {
+    let _terminal = RawTerminal::enter()?;
+    let input = spawn_input();
+    let mut tui = Tui {
+        paused: false,
+        cursor: Coords { x: game.width() as i32 / 2, y: 0 },
+        material: Cells::Sand,
+        view: Coords { x: 0, y: 0 },
+    };
+    let mut renderer = TerminalRenderer::new();
+    let mut frame = Frame::new();
+    let mut stdout = io::stdout();
+    let mut next_tick = Instant::now();
+    loop {
+        tui.draw(game, &mut renderer, &mut stdout)?;
+        match input.recv_timeout(next_tick.saturating_duration_since(Instant::now())) {
+            Ok(bytes) => {
+                for key in parse_keys(&bytes) {
+                    if !tui.handle(game, &mut frame, &mut renderer, key) {
+                        return Ok(());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if !tui.paused {
+                    game.update(&mut frame);
+                }
+                next_tick = Instant::now() + TICK;
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tui() -> Tui {
+        Tui { paused: false, cursor: Coords { x: 0, y: 0 }, material: Cells::Sand, view: Coords { x: 0, y: 0 } }
+    }
+
+    #[test]
+    fn parse_keys_reads_escape_sequences_and_plain_bytes() {
+        let keys = parse_keys(b"\x1b[A\x1b[Bq\x1b[C\x1b[D\x1bOP\x1bOS 3");
+        assert_eq!(
+            keys,
+            [
+                Key::Up,
+                Key::Down,
+                Key::Char(b'q'),
+                Key::Right,
+                Key::Left,
+                Key::Function(0),
+                Key::Function(3),
+                Key::Char(b' '),
+                Key::Char(b'3'),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_keys_keeps_unknown_and_cut_off_sequences_as_bytes() {
+        assert_eq!(parse_keys(b""), []);
+        assert_eq!(parse_keys(b"\x1b"), [Key::Char(0x1b)]);
+        assert_eq!(parse_keys(b"\x1b["), [Key::Char(0x1b), Key::Char(b'[')]);
+        assert_eq!(parse_keys(b"\x1b[Z"), [Key::Char(0x1b), Key::Char(b'['), Key::Char(b'Z')]);
+    }
+
+    #[test]
+    fn cursor_stays_inside_the_world() {
+        let (mut game, mut frame, mut renderer) = (Game::with_seed(3, 2, 0), Frame::new(), TerminalRenderer::new());
+        let mut tui = tui();
+        for key in [Key::Left, Key::Up, Key::Right, Key::Right, Key::Right, Key::Down, Key::Down] {
+            assert!(tui.handle(&mut game, &mut frame, &mut renderer, key));
+        }
+        assert_eq!(tui.cursor, Coords { x: 2, y: 1 });
+    }
+
+    #[test]
+    fn empty_worlds_ignore_cursor_movement() {
+        let mut renderer = TerminalRenderer::new();
+        for (width, height) in [(0, 0), (0, 3), (3, 0)] {
+            let (mut game, mut frame) = (Game::with_seed(width, height, 0), Frame::new());
+            let mut tui = tui();
+            for key in [Key::Right, Key::Down, Key::Char(b'p'), Key::Char(b'b')] {
+                assert!(tui.handle(&mut game, &mut frame, &mut renderer, key));
+            }
+            assert_eq!(tui.cursor, Coords { x: 0, y: 0 });
+        }
+    }
+}
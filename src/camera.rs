@@ -0,0 +1,126 @@
+use crate::game::Coords;
+
+/// Rectangle of cells.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Area {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Area {
+    /// Returns the part of the area inside a world of the given size.
+    pub fn clip(&self, world_width: u32, world_height: u32) -> Area {
+        let left = self.x.clamp(0, world_width as i32);
+        let top = self.y.clamp(0, world_height as i32);
+        let right = (self.x + self.width as i32).clamp(left, world_width as i32);
+        let bottom = (self.y + self.height as i32).clamp(top, world_height as i32);
+        Area {
+            x: left,
+            y: top,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, coords: Coords) -> bool {
+        coords.x >= self.x
+            && coords.y >= self.y
+            && coords.x < self.x + self.width as i32
+            && coords.y < self.y + self.height as i32
+    }
+}
+
+/// Position and zoom of the view over the world.
+///
+/// World positions are in cells, screen positions in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    /// World position shown in the top left corner of the screen.
+    pub x: f32,
+    pub y: f32,
+    /// Size of a cell on the screen, in pixels.
+    pub zoom: f32,
+    pub screen_width: u32,
+    pub screen_height: u32,
+}
+
+impl Camera {
+    pub const MIN_ZOOM: f32 = 1.0;
+    pub const MAX_ZOOM: f32 = 128.0;
+
+    pub fn new(screen_width: u32, screen_height: u32, zoom: f32) -> Camera {
+        Camera {
+            x: 0.0,
+            y: 0.0,
+            zoom: zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM),
+            screen_width,
+            screen_height,
+        }
+    }
+
+    /// Zooms so the whole world fits on the screen and centers it.
+    pub fn fit(&mut self, world_width: u32, world_height: u32) {
+        let zoom_x = self.screen_width as f32 / world_width.max(1) as f32;
+        let zoom_y = self.screen_height as f32 / world_height.max(1) as f32;
+        self.zoom = zoom_x.min(zoom_y).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.center_on(world_width as f32 / 2.0, world_height as f32 / 2.0);
+    }
+
+    /// Moves the camera so the world position is in the middle of the screen.
+    pub fn center_on(&mut self, x: f32, y: f32) {
+        self.x = x - self.screen_width as f32 / self.zoom / 2.0;
+        self.y = y - self.screen_height as f32 / self.zoom / 2.0;
+    }
+
+    /// Moves the view by the given amount of screen pixels.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.x += dx / self.zoom;
+        self.y += dy / self.zoom;
+    }
+
+    /// Multiplies the zoom, keeping the world position under the screen point in place.
+    pub fn zoom_at(&mut self, factor: f32, screen_x: f32, screen_y: f32) {
+        let (world_x, world_y) = self.screen_to_world(screen_x, screen_y);
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.x = world_x - screen_x / self.zoom;
+        self.y = world_y - screen_y / self.zoom;
+    }
+
+    pub fn resize_screen(&mut self, screen_width: u32, screen_height: u32) {
+        self.screen_width = screen_width;
+        self.screen_height = screen_height;
+    }
+
+    pub fn screen_to_world(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
+        (self.x + screen_x / self.zoom, self.y + screen_y / self.zoom)
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.x) * self.zoom, (y - self.y) * self.zoom)
+    }
+
+    /// Returns the coordinates of the cell under the screen point.
+    pub fn screen_to_cell(&self, screen_x: f32, screen_y: f32) -> Coords {
+        let (x, y) = self.screen_to_world(screen_x, screen_y);
+        Coords { x: x.floor() as i32, y: y.floor() as i32 }
+    }
+
+    /// Returns the cells of a world of the given size that are at least partly on the screen.
+    pub fn visible_area(&self, world_width: u32, world_height: u32) -> Area {
+        let (right, bottom) = self.screen_to_world(self.screen_width as f32, self.screen_height as f32);
+        let (left, top) = (self.x.floor() as i32, self.y.floor() as i32);
+        let area = Area {
+            x: left,
+            y: top,
+            width: (right.ceil() as i32 - left).max(0) as u32,
+            height: (bottom.ceil() as i32 - top).max(0) as u32,
+        };
+        area.clip(world_width, world_height)
+    }
+}
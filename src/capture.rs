@@ -0,0 +1,188 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::game::Game;
+use crate::image::ImageError;
+use crate::overlay::Overlays;
+use crate::render::PixelBuffer;
+
+/// Error returned when capturing frames fails.
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    Gif(gif::EncodingError),
+    Png(ImageError),
+    /// Gif images can't be larger than 65535 pixels on either side.
+    TooLarge { width: u32, height: u32 },
+    /// The world was resized during a gif capture.
+    SizeChanged,
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CaptureError::Io(err) => write!(f, "io error: {}", err),
+            CaptureError::Gif(err) => write!(f, "failed to encode gif: {}", err),
+            CaptureError::Png(err) => write!(f, "failed to write png: {}", err),
+            CaptureError::TooLarge { width, height } => write!(f, "{}x{} is too large for a gif", width, height),
+            CaptureError::SizeChanged => write!(f, "the world changed size during the capture"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CaptureError::Io(err) => Some(err),
+            CaptureError::Gif(err) => Some(err),
+            CaptureError::Png(err) => Some(err),
+            CaptureError::TooLarge { .. } | CaptureError::SizeChanged => None,
+        }
+    }
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(err: io::Error) -> CaptureError {
+        CaptureError::Io(err)
+    }
+}
+
+impl From<gif::EncodingError> for CaptureError {
+    fn from(err: gif::EncodingError) -> CaptureError {
+        CaptureError::Gif(err)
+    }
+}
+
+impl From<ImageError> for CaptureError {
+    fn from(err: ImageError) -> CaptureError {
+        CaptureError::Png(err)
+    }
+}
+
+enum Output {
+    /// The encoder is created with the first frame, once the image size is known.
+    Gif {
+        writer: Option<BufWriter<File>>,
+        encoder: Option<gif::Encoder<BufWriter<File>>>,
+        size: (u16, u16),
+    },
+    /// Numbered png files in a directory.
+    Png { directory: PathBuf },
+}
+
+/// Captures every Nth offered frame of the world to an animated gif or a png sequence.
+pub struct Capture {
+    output: Output,
+    every: u32,
+    scale: u32,
+    /// Time between two offered frames, used for the gif frame delays.
+    frame_time: Duration,
+    pub overlays: Overlays,
+    /// Captures cells unlit and without the background, so png frames can be read back as worlds.
+    pub plain_colors: bool,
+    offered: u64,
+    captured: usize,
+}
+
+impl Capture {
+    /// Paths ending in `.gif` capture an animated gif, any other path is a directory for png files.
+    pub fn create(path: impl AsRef<Path>, every: u32) -> Result<Capture, CaptureError> {
+        let path = path.as_ref();
+        let output = if path.extension().is_some_and(|ext| ext == "gif") {
+            Output::Gif {
+                writer: Some(BufWriter::new(File::create(path)?)),
+                encoder: None,
+                size: (0, 0),
+            }
+        } else {
+            fs::create_dir_all(path)?;
+            Output::Png { directory: path.to_path_buf() }
+        };
+        Ok(Capture {
+            output,
+            every: every.max(1),
+            scale: 1,
+            frame_time: Duration::from_millis(50),
+            overlays: Overlays::NONE,
+            plain_colors: false,
+            offered: 0,
+            captured: 0,
+        })
+    }
+
+    /// Draws every cell as a `scale` by `scale` square.
+    pub fn with_scale(mut self, scale: u32) -> Capture {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn with_frame_time(mut self, frame_time: Duration) -> Capture {
+        self.frame_time = frame_time;
+        self
+    }
+
+    /// Returns the number of frames written so far.
+    pub fn captured(&self) -> usize {
+        self.captured
+    }
+
+    /// Offers a frame, it is written if it's the Nth one. Returns whether it was written.
+    pub fn capture(&mut self, game: &Game) -> Result<bool, CaptureError> {
+        let due = self.offered.is_multiple_of(self.every as u64);
+        self.offered += 1;
+        if !due {
+            return Ok(false);
+        }
+        let mut buffer = PixelBuffer::new(self.scale);
+        buffer.overlays = self.overlays;
+        buffer.plain_colors = self.plain_colors;
+        game.draw(&mut buffer, game.area());
+        self.write(buffer)?;
+        self.captured += 1;
+        Ok(true)
+    }
+
+    fn write(&mut self, buffer: PixelBuffer) -> Result<(), CaptureError> {
+        match &mut self.output {
+            Output::Gif { writer, encoder, size } => {
+                let (width, height) = (buffer.width(), buffer.height());
+                let frame_size = match (u16::try_from(width), u16::try_from(height)) {
+                    (Ok(width), Ok(height)) => (width, height),
+                    _ => return Err(CaptureError::TooLarge { width, height }),
+                };
+                let encoder = match encoder {
+                    Some(encoder) if *size != frame_size => return Err(CaptureError::SizeChanged),
+                    Some(encoder) => encoder,
+                    None => {
+                        let writer = writer.take().expect("gif writer is only taken by the encoder");
+                        let mut created = gif::Encoder::new(writer, frame_size.0, frame_size.1, &[])?;
+                        created.set_repeat(gif::Repeat::Infinite)?;
+                        *size = frame_size;
+                        encoder.insert(created)
+                    }
+                };
+                let mut pixels = buffer.into_pixels();
+                let mut frame = gif::Frame::from_rgba_speed(frame_size.0, frame_size.1, &mut pixels, 10);
+                // gif delays are in hundredths of a second
+                let delay = self.frame_time.as_millis() * self.every as u128 / 10;
+                frame.delay = delay.min(u16::MAX as u128) as u16;
+                encoder.write_frame(&frame)?;
+            }
+            Output::Png { directory } => {
+                buffer.save_png(directory.join(format!("{:05}.png", self.captured)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes the file and returns the number of captured frames.
+    pub fn finish(self) -> Result<usize, CaptureError> {
+        if let Output::Gif { encoder: Some(encoder), .. } = self.output {
+            let mut writer = encoder.into_inner()?;
+            io::Write::flush(&mut writer)?;
+        }
+        Ok(self.captured)
+    }
+}
@@ -0,0 +1,287 @@
+//! Little endian binary encoding shared by the replay and save file formats.
+
+use std::io::{self, Read, Write};
+
+use crate::color::Color;
+use crate::game::{Action, BackgroundAction, Cell, CellAction, Cells, ColorMode, ConflictPolicy, Coords};
+
+/// Error returned when a file can't be decoded.
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(io::Error),
+    /// The file doesn't start with the expected magic bytes.
+    BadMagic,
+    /// The file was written by a different version of the format.
+    UnsupportedVersion(u16),
+    /// The file contains data that can't be valid.
+    Corrupt(&'static str),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "io error: {}", err),
+            DecodeError::BadMagic => write!(f, "not a valid file, bad magic bytes"),
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+            DecodeError::Corrupt(reason) => write!(f, "corrupt file: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> DecodeError {
+        DecodeError::Io(err)
+    }
+}
+
+pub fn write_u8(w: &mut impl Write, value: u8) -> io::Result<()> {
+    w.write_all(&[value])
+}
+
+pub fn write_u16(w: &mut impl Write, value: u16) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u32(w: &mut impl Write, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+pub fn write_u64(w: &mut impl Write, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+pub fn write_i32(w: &mut impl Write, value: i32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+pub fn read_u8(r: &mut impl Read) -> Result<u8, DecodeError> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub fn read_u16(r: &mut impl Read) -> Result<u16, DecodeError> {
+    let mut buf = [0; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+pub fn read_u32(r: &mut impl Read) -> Result<u32, DecodeError> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub fn read_u64(r: &mut impl Read) -> Result<u64, DecodeError> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn read_i32(r: &mut impl Read) -> Result<i32, DecodeError> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+/// Writes the magic bytes and format version.
+pub fn write_header(w: &mut impl Write, magic: &[u8; 4], version: u16) -> io::Result<()> {
+    w.write_all(magic)?;
+    write_u16(w, version)
+}
+
+/// Reads the magic bytes and returns the format version.
+pub fn read_header(r: &mut impl Read, magic: &[u8; 4]) -> Result<u16, DecodeError> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    if &buf != magic {
+        return Err(DecodeError::BadMagic);
+    }
+    read_u16(r)
+}
+
+pub fn write_kind(w: &mut impl Write, kind: Cells) -> io::Result<()> {
+    write_u8(w, kind.index() as u8)
+}
+
+pub fn read_kind(r: &mut impl Read) -> Result<Cells, DecodeError> {
+    Cells::from_index(read_u8(r)? as usize).ok_or(DecodeError::Corrupt("unknown cell kind"))
+}
+
+pub fn write_color_mode(w: &mut impl Write, color_mode: ColorMode) -> io::Result<()> {
+    match color_mode {
+        ColorMode::Static(color) => {
+            write_u8(w, 0)?;
+            w.write_all(&[color.r, color.g, color.b, color.a])
+        }
+        ColorMode::Dynamic => write_u8(w, 1),
+    }
+}
+
+pub fn read_color_mode(r: &mut impl Read) -> Result<ColorMode, DecodeError> {
+    match read_u8(r)? {
+        0 => {
+            let mut buf = [0; 4];
+            r.read_exact(&mut buf)?;
+            Ok(ColorMode::Static(Color::rgba(buf[0], buf[1], buf[2], buf[3])))
+        }
+        1 => Ok(ColorMode::Dynamic),
+        _ => Err(DecodeError::Corrupt("unknown color mode")),
+    }
+}
+
+pub fn write_cell(w: &mut impl Write, cell: &Cell) -> io::Result<()> {
+    write_kind(w, cell.kind)?;
+    write_color_mode(w, cell.color_mode)?;
+    write_i32(w, cell.hardness)
+}
+
+pub fn read_cell(r: &mut impl Read) -> Result<Cell, DecodeError> {
+    let kind = read_kind(r)?;
+    let color_mode = read_color_mode(r)?;
+    let hardness = read_i32(r)?;
+    Ok(Cell { kind, color_mode, hardness })
+}
+
+/// Writes the materials run-length encoded, as pairs of run length and material.
+pub fn write_kind_runs(w: &mut impl Write, kinds: &[Cells]) -> io::Result<()> {
+    let mut kinds = kinds.iter().peekable();
+    while let Some(kind) = kinds.next() {
+        let mut run = 1u32;
+        while kinds.next_if_eq(&kind).is_some() {
+            run += 1;
+        }
+        write_u32(w, run)?;
+        write_kind(w, *kind)?;
+    }
+    Ok(())
+}
+
+pub fn read_kind_runs(r: &mut impl Read, count: usize) -> Result<Vec<Cells>, DecodeError> {
+    let mut kinds = Vec::new();
+    while kinds.len() < count {
+        let run = read_u32(r)? as usize;
+        if run == 0 || kinds.len() + run > count {
+            return Err(DecodeError::Corrupt("material run doesn't fit the world"));
+        }
+        let kind = read_kind(r)?;
+        kinds.resize(kinds.len() + run, kind);
+    }
+    Ok(kinds)
+}
+
+pub fn write_coords(w: &mut impl Write, coords: Coords) -> io::Result<()> {
+    write_i32(w, coords.x)?;
+    write_i32(w, coords.y)
+}
+
+pub fn read_coords(r: &mut impl Read) -> Result<Coords, DecodeError> {
+    let x = read_i32(r)?;
+    let y = read_i32(r)?;
+    Ok(Coords { x, y })
+}
+
+pub fn write_action(w: &mut impl Write, action: &Action) -> io::Result<()> {
+    match action {
+        Action::Cell(coords, cell_action) => {
+            write_u8(w, 0)?;
+            write_coords(w, *coords)?;
+            match cell_action {
+                CellAction::Set(cell) => {
+                    write_u8(w, 0)?;
+                    write_cell(w, cell)
+                }
+                CellAction::SetKind(kind) => {
+                    write_u8(w, 1)?;
+                    write_kind(w, *kind)
+                }
+                CellAction::SetColorMode(color_mode) => {
+                    write_u8(w, 2)?;
+                    write_color_mode(w, *color_mode)
+                }
+                CellAction::SetHardness(hardness) => {
+                    write_u8(w, 3)?;
+                    write_i32(w, *hardness)
+                }
+                CellAction::Clear => write_u8(w, 4),
+            }
+        }
+        Action::Swap(coords1, coords2) => {
+            write_u8(w, 1)?;
+            write_coords(w, *coords1)?;
+            write_coords(w, *coords2)
+        }
+        Action::Background(coords, background_action) => {
+            write_u8(w, 2)?;
+            write_coords(w, *coords)?;
+            match background_action {
+                BackgroundAction::Set(kind) => {
+                    write_u8(w, 0)?;
+                    write_kind(w, *kind)
+                }
+                BackgroundAction::Clear => write_u8(w, 1),
+            }
+        }
+    }
+}
+
+pub fn read_action(r: &mut impl Read) -> Result<Action, DecodeError> {
+    match read_u8(r)? {
+        0 => {
+            let coords = read_coords(r)?;
+            let cell_action = match read_u8(r)? {
+                0 => CellAction::Set(read_cell(r)?),
+                1 => CellAction::SetKind(read_kind(r)?),
+                2 => CellAction::SetColorMode(read_color_mode(r)?),
+                3 => CellAction::SetHardness(read_i32(r)?),
+                4 => CellAction::Clear,
+                _ => return Err(DecodeError::Corrupt("unknown cell action")),
+            };
+            Ok(Action::Cell(coords, cell_action))
+        }
+        1 => {
+            let coords1 = read_coords(r)?;
+            let coords2 = read_coords(r)?;
+            Ok(Action::Swap(coords1, coords2))
+        }
+        2 => {
+            let coords = read_coords(r)?;
+            let background_action = match read_u8(r)? {
+                0 => BackgroundAction::Set(read_kind(r)?),
+                1 => BackgroundAction::Clear,
+                _ => return Err(DecodeError::Corrupt("unknown background action")),
+            };
+            Ok(Action::Background(coords, background_action))
+        }
+        _ => Err(DecodeError::Corrupt("unknown action")),
+    }
+}
+
+pub fn write_policy(w: &mut impl Write, policy: ConflictPolicy) -> io::Result<()> {
+    let tag = match policy {
+        ConflictPolicy::Overwrite => 0,
+        ConflictPolicy::FirstWins => 1,
+        ConflictPolicy::Random => 2,
+        ConflictPolicy::Reject => 3,
+    };
+    write_u8(w, tag)
+}
+
+pub fn read_policy(r: &mut impl Read) -> Result<ConflictPolicy, DecodeError> {
+    match read_u8(r)? {
+        0 => Ok(ConflictPolicy::Overwrite),
+        1 => Ok(ConflictPolicy::FirstWins),
+        2 => Ok(ConflictPolicy::Random),
+        3 => Ok(ConflictPolicy::Reject),
+        _ => Err(DecodeError::Corrupt("unknown conflict policy")),
+    }
+}
@@ -0,0 +1,45 @@
+/// RGBA colour used by the simulation, independent of any rendering backend.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const YELLOW: Color = Color::rgb(255, 255, 0);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// Paints the colour over this one, using its alpha as opacity. Keeps this colour's alpha.
+    pub fn blend(self, over: Color) -> Color {
+        let mix = |under: u8, over_channel: u8| {
+            ((under as u32 * (255 - over.a as u32) + over_channel as u32 * over.a as u32) / 255) as u8
+        };
+        Color::rgba(mix(self.r, over.r), mix(self.g, over.g), mix(self.b, over.b), self.a)
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl From<Color> for sfml::graphics::Color {
+    fn from(color: Color) -> sfml::graphics::Color {
+        sfml::graphics::Color::rgba(color.r, color.g, color.b, color.a)
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl From<sfml::graphics::Color> for Color {
+    fn from(color: sfml::graphics::Color) -> Color {
+        Color::rgba(color.r, color.g, color.b, color.a)
+    }
+}
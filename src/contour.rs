@@ -0,0 +1,158 @@
+use crate::camera::Area;
+use crate::game::{Cells, Game, Phase};
+
+/// Point in world coordinates, one unit per cell.
+pub type Point = (f32, f32);
+
+/// Corners of a marching square, in the order its perimeter is walked.
+const CORNERS: [(i32, i32); 4] = [(0, 0), (1, 0), (1, 1), (0, 1)];
+
+/// Boundaries of a region of cells traced with marching squares.
+///
+/// The region is sampled at the centres of the cells, so its boundary cuts diagonally through corners
+/// instead of following the cell grid. Outside of the world nothing is inside, the samples there are
+/// pulled onto the world border so regions touching it reach the edge and stay closed along it.
+#[derive(Debug, Clone, Default)]
+pub struct Contours {
+    /// Convex polygons covering the inside of the region, one per marching square.
+    pub fills: Vec<Vec<Point>>,
+    /// Segments of the boundary between the inside and the outside.
+    pub segments: Vec<(Point, Point)>,
+}
+
+impl Contours {
+    /// Traces the region of cells of the area, clipped to the world, for which `inside` returns true.
+    pub fn extract(game: &Game, area: Area, inside: impl Fn(i32, i32) -> bool) -> Contours {
+        let area = area.clip(game.width(), game.height());
+        let mut contours = Contours::default();
+        if area.is_empty() {
+            return contours;
+        }
+        let is_inside = |(x, y): (i32, i32)| game.cell_exists(x, y) && inside(x, y);
+        let (width, height) = (game.width() as f32, game.height() as f32);
+        let sample = |(x, y): (i32, i32)| ((x as f32 + 0.5).clamp(0.0, width), (y as f32 + 0.5).clamp(0.0, height));
+        // the squares between the centres of the area's cells and of the cells around it
+        for y in area.y - 1..area.y + area.height as i32 {
+            for x in area.x - 1..area.x + area.width as i32 {
+                let corners = CORNERS.map(|(dx, dy)| (x + dx, y + dy));
+                let states = corners.map(is_inside);
+                if !states.contains(&true) {
+                    continue;
+                }
+                // the inside corners and the midpoints of the edges crossing the boundary
+                let mut polygon = Vec::with_capacity(6);
+                let mut crossings = Vec::with_capacity(6);
+                for i in 0..4 {
+                    let next = (i + 1) % 4;
+                    if states[i] {
+                        polygon.push(sample(corners[i]));
+                        crossings.push(false);
+                    }
+                    if states[i] != states[next] {
+                        let (a, b) = (corners[i], corners[next]);
+                        // regions end at the world border rather than halfway to it
+                        let crossing = match (game.cell_exists(a.0, a.1), game.cell_exists(b.0, b.1)) {
+                            (false, _) => sample(a),
+                            (_, false) => sample(b),
+                            _ => {
+                                let (a, b) = (sample(a), sample(b));
+                                ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+                            }
+                        };
+                        polygon.push(crossing);
+                        crossings.push(true);
+                    }
+                }
+                for i in 0..polygon.len() {
+                    let next = (i + 1) % polygon.len();
+                    if crossings[i] && crossings[next] {
+                        contours.segments.push((polygon[i], polygon[next]));
+                    }
+                }
+                contours.fills.push(polygon);
+            }
+        }
+        contours
+    }
+
+    /// Returns whether the point lies inside the region.
+    pub fn contains(&self, point: Point) -> bool {
+        self.fills.iter().any(|polygon| {
+            (0..polygon.len()).all(|i| {
+                let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+                (b.0 - a.0) * (point.1 - a.1) - (b.1 - a.1) * (point.0 - a.0) >= 0.0
+            })
+        })
+    }
+
+    /// Returns the distance from the point to the closest boundary, `None` if the region is empty.
+    pub fn distance(&self, point: Point) -> Option<f32> {
+        self.segments.iter().map(|&(a, b)| segment_distance(point, a, b)).min_by(f32::total_cmp)
+    }
+}
+
+fn segment_distance(point: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 { 0.0 } else { (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length).clamp(0.0, 1.0) };
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((point.0 - x).powi(2) + (point.1 - y).powi(2)).sqrt()
+}
+
+impl Cells {
+    /// Returns whether the material is drawn as smooth shapes by the contour renderer.
+    pub fn is_contoured(&self) -> bool {
+        matches!(self.phase(), Phase::Solid | Phase::Liquid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Cell;
+
+    /// World of 10 by 10 cells with a 3 by 3 block of wall from (4, 4) to (6, 6).
+    fn walled_square() -> Game {
+        let mut game = Game::with_seed(10, 10, 0);
+        for y in 4..7 {
+            for x in 4..7 {
+                game.set_cell(x, y, Cell::new(Cells::Wall));
+            }
+        }
+        game
+    }
+
+    fn wall_contours(game: &Game) -> Contours {
+        let area = Area { x: 0, y: 0, width: game.width(), height: game.height() };
+        Contours::extract(game, area, |x, y| game.get_cell(x, y).kind == Cells::Wall)
+    }
+
+    #[test]
+    fn filled_squares_contain_their_cells_but_not_their_corners() {
+        let contours = wall_contours(&walled_square());
+        for point in [(5.5, 5.5), (4.1, 5.5), (6.9, 5.5), (5.5, 4.1), (4.4, 4.4)] {
+            assert!(contours.contains(point), "{:?} should be inside", point);
+        }
+        // the boundary runs halfway between cell centres and cuts the corners diagonally
+        for point in [(2.0, 2.0), (3.9, 5.5), (7.1, 5.5), (4.2, 4.2), (8.5, 8.5)] {
+            assert!(!contours.contains(point), "{:?} should be outside", point);
+        }
+    }
+
+    #[test]
+    fn distance_is_measured_to_the_boundary() {
+        let contours = wall_contours(&walled_square());
+        assert!((contours.distance((2.0, 5.5)).unwrap() - 2.0).abs() < 1e-5);
+        assert!((contours.distance((5.5, 9.0)).unwrap() - 2.0).abs() < 1e-5);
+        assert_eq!(wall_contours(&Game::with_seed(4, 4, 0)).distance((1.0, 1.0)), None);
+    }
+
+    #[test]
+    fn regions_touching_the_border_reach_it() {
+        let mut game = Game::with_seed(4, 4, 0);
+        game.set_cell(0, 1, Cell::new(Cells::Wall));
+        let contours = wall_contours(&game);
+        assert!(contours.contains((0.05, 1.5)));
+        assert!(!contours.contains((1.1, 1.5)));
+    }
+}
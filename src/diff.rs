@@ -0,0 +1,221 @@
+use crate::game::{Action, BackgroundAction, Cell, CellAction, Cells, Coords, Frame, Game};
+
+/// Copy of a world's cells and background, to be compared with the game later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    /// Cells row by row.
+    pub cells: Vec<Cell>,
+    /// Background row by row.
+    pub background: Vec<Cells>,
+}
+
+/// Error returned when comparing worlds of different sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimensionMismatch {
+    pub before: (u32, u32),
+    pub after: (u32, u32),
+}
+
+impl std::fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "can't compare a {}x{} world with a {}x{} world",
+            self.before.0, self.before.1, self.after.0, self.after.1
+        )
+    }
+}
+
+impl std::error::Error for DimensionMismatch {}
+
+/// Horizontal run of changed cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRun {
+    pub x: i32,
+    pub y: i32,
+    pub len: u32,
+}
+
+/// Cells and background that differ between two worlds, with their new value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorldDiff {
+    /// Changed cells, row by row.
+    pub changes: Vec<(Coords, Cell)>,
+    /// Changed background, row by row.
+    pub background_changes: Vec<(Coords, Cells)>,
+}
+
+impl WorldDiff {
+    /// Compares two worlds, the diff turns `before` into `after`.
+    pub fn between(before: &Game, after: &Game) -> Result<WorldDiff, DimensionMismatch> {
+        Self::compare(
+            (before.width(), before.height()),
+            (before.cells(), before.background()),
+            (after.width(), after.height()),
+            (after.cells(), after.background()),
+        )
+    }
+
+    /// Compares a snapshot with the current state of the game.
+    pub fn since(snapshot: &Snapshot, game: &Game) -> Result<WorldDiff, DimensionMismatch> {
+        Self::compare(
+            (snapshot.width, snapshot.height),
+            (&snapshot.cells, &snapshot.background),
+            (game.width(), game.height()),
+            (game.cells(), game.background()),
+        )
+    }
+
+    fn compare(
+        before_size: (u32, u32),
+        before: (&[Cell], &[Cells]),
+        after_size: (u32, u32),
+        after: (&[Cell], &[Cells]),
+    ) -> Result<WorldDiff, DimensionMismatch> {
+        if before_size != after_size {
+            return Err(DimensionMismatch { before: before_size, after: after_size });
+        }
+        let width = before_size.0.max(1) as usize;
+        Ok(WorldDiff {
+            changes: Self::changed(width, before.0, after.0),
+            background_changes: Self::changed(width, before.1, after.1),
+        })
+    }
+
+    /// Returns the coordinates and new value of every differing element of two layers stored row by row.
+    fn changed<T: Copy + PartialEq>(width: usize, before: &[T], after: &[T]) -> Vec<(Coords, T)> {
+        before
+            .iter()
+            .zip(after)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (_, after))| {
+                let coords = Coords { x: (index % width) as i32, y: (index / width) as i32 };
+                (coords, *after)
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.background_changes.is_empty()
+    }
+
+    /// Returns the number of changes, a cell whose background changed too counts twice.
+    pub fn len(&self) -> usize {
+        self.changes.len() + self.background_changes.len()
+    }
+
+    /// Returns the coordinates of every cell changed on either layer, row by row.
+    fn changed_coords(&self) -> Vec<Coords> {
+        let mut coords: Vec<Coords> = self
+            .changes
+            .iter()
+            .map(|(coords, _)| *coords)
+            .chain(self.background_changes.iter().map(|(coords, _)| *coords))
+            .collect();
+        coords.sort_by_key(|coords| (coords.y, coords.x));
+        coords.dedup();
+        coords
+    }
+
+    /// Returns the actions that apply the diff.
+    pub fn to_actions(&self) -> Vec<Action> {
+        let cells = self.changes.iter().map(|(coords, cell)| Action::Cell(*coords, CellAction::Set(*cell)));
+        let background = self
+            .background_changes
+            .iter()
+            .map(|(coords, kind)| Action::Background(*coords, BackgroundAction::Set(*kind)));
+        cells.chain(background).collect()
+    }
+
+    /// Returns a frame that applies the diff.
+    pub fn to_frame(&self) -> Frame {
+        let mut frame = Frame::new();
+        for action in self.to_actions() {
+            frame.add_action(action);
+        }
+        frame
+    }
+
+    /// Returns the top left and bottom right corner of the changed area, both included.
+    pub fn bounds(&self) -> Option<(Coords, Coords)> {
+        let changed = self.changed_coords();
+        let first = changed.first()?;
+        let mut min = *first;
+        let mut max = *first;
+        for coords in &changed {
+            min = Coords { x: min.x.min(coords.x), y: min.y.min(coords.y) };
+            max = Coords { x: max.x.max(coords.x), y: max.y.max(coords.y) };
+        }
+        Some((min, max))
+    }
+
+    /// Returns the changed area as horizontal runs of neighbouring cells.
+    pub fn runs(&self) -> Vec<DiffRun> {
+        let mut runs: Vec<DiffRun> = Vec::new();
+        for coords in self.changed_coords() {
+            match runs.last_mut() {
+                Some(run) if run.y == coords.y && run.x + run.len as i32 == coords.x => run.len += 1,
+                _ => runs.push(DiffRun { x: coords.x, y: coords.y, len: 1 }),
+            }
+        }
+        runs
+    }
+}
+
+impl std::fmt::Display for WorldDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (coords, cell) in &self.changes {
+            writeln!(f, "{}, {}: {:?}", coords.x, coords.y, cell.kind)?;
+        }
+        for (coords, kind) in &self.background_changes {
+            writeln!(f, "{}, {}: background {:?}", coords.x, coords.y, kind)?;
+        }
+        Ok(())
+    }
+}
+
+impl Game {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            width: self.width(),
+            height: self.height(),
+            cells: self.cells().to_vec(),
+            background: self.background().to_vec(),
+        }
+    }
+
+    /// Returns the cells and background that differ from the other game, with the other game's values.
+    pub fn diff(&self, other: &Game) -> Result<WorldDiff, DimensionMismatch> {
+        WorldDiff::between(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_edits_show_up_in_the_diff() {
+        let mut game = Game::with_seed(4, 3, 0);
+        let snapshot = game.snapshot();
+        let mut frame = Frame::new();
+        frame.add_action(Action::Background(Coords { x: 2, y: 1 }, BackgroundAction::Set(Cells::Wall)));
+        frame.add_action(Action::Cell(Coords { x: 1, y: 1 }, CellAction::SetKind(Cells::Sand)));
+        game.apply_frame(&mut frame);
+
+        let diff = WorldDiff::since(&snapshot, &game).unwrap();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff.background_changes, [(Coords { x: 2, y: 1 }, Cells::Wall)]);
+        assert_eq!(diff.bounds(), Some((Coords { x: 1, y: 1 }, Coords { x: 2, y: 1 })));
+        assert_eq!(diff.runs(), [DiffRun { x: 1, y: 1, len: 2 }]);
+
+        // applying the diff to the old world rebuilds the new one
+        let mut copy = Game::with_seed(4, 3, 0);
+        copy.apply_frame(&mut diff.to_frame());
+        assert!(copy.diff(&game).unwrap().is_empty());
+        assert_eq!(copy.background(), game.background());
+    }
+}
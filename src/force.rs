@@ -0,0 +1,191 @@
+use rand::prelude::*;
+
+use crate::game::{Action, Cell, Coords, Frame, Game, Mask};
+
+/// Area of the world affected by a force field.
+#[derive(Debug, Clone, Copy)]
+pub enum Region {
+    Rect {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+    Mask(Mask),
+}
+
+impl Region {
+    pub fn contains(&self, coords: Coords) -> bool {
+        match self {
+            Region::Rect { x, y, width, height } => {
+                coords.x >= *x
+                    && coords.y >= *y
+                    && coords.x < x + *width as i32
+                    && coords.y < y + *height as i32
+            }
+            Region::Mask(mask) => mask.contains(coords),
+        }
+    }
+}
+
+/// Pushes powders and liquids inside its region in a given direction.
+///
+/// Fans, vents and wind are all just force fields with a different strength and turbulence.
+#[derive(Debug, Clone, Copy)]
+pub struct ForceField {
+    pub region: Region,
+    /// Direction of the force. Does not have to be normalized.
+    pub direction: (f32, f32),
+    /// Chance (0.0 - 1.0) that an updated cell inside the region gets pushed.
+    pub strength: f32,
+    /// Maximum random deviation from the direction, in radians.
+    pub turbulence: f32,
+}
+
+impl ForceField {
+    pub fn new(region: Region, direction: (f32, f32), strength: f32) -> ForceField {
+        ForceField { region, direction, strength, turbulence: 0.0 }
+    }
+
+    pub fn with_turbulence(mut self, turbulence: f32) -> ForceField {
+        self.turbulence = turbulence;
+        self
+    }
+
+    /// Returns the neighbouring offset the cell would be pushed to.
+    fn step(&self, rng: &mut impl Rng) -> Option<Coords> {
+        let (dx, dy) = self.direction;
+        if dx == 0.0 && dy == 0.0 {
+            return None;
+        }
+        let mut angle = dy.atan2(dx);
+        if self.turbulence > 0.0 {
+            angle += rng.gen_range(-self.turbulence..=self.turbulence);
+        }
+        Some(Self::offset(angle))
+    }
+
+    /// Returns every offset `step` can pick, whatever the turbulence draws.
+    fn steps(&self) -> Vec<Coords> {
+        let (dx, dy) = self.direction;
+        if dx == 0.0 && dy == 0.0 {
+            return Vec::new();
+        }
+        let angle = dy.atan2(dx);
+        let turbulence = self.turbulence.max(0.0);
+        let (low, high) = (angle - turbulence, angle + turbulence);
+        // the rounded offset only changes at multiples of 30 degrees, so one angle per sector covers them all
+        let sector = std::f32::consts::PI / 6.0;
+        let mut angles = vec![low, high];
+        let mut k = (low / sector).floor();
+        while k * sector <= high {
+            angles.push(((k + 0.5) * sector).clamp(low, high));
+            k += 1.0;
+        }
+        let mut steps: Vec<Coords> = angles.into_iter().map(Self::offset).collect();
+        steps.sort_by_key(|step| (step.x, step.y));
+        steps.dedup();
+        steps
+    }
+
+    fn offset(angle: f32) -> Coords {
+        Coords { x: angle.cos().round() as i32, y: angle.sin().round() as i32 }
+    }
+
+    /// Adds a swap to the frame if the field pushes the cell.
+    ///
+    /// Returns true if the cell was pushed.
+    pub fn push(&self, game: &Game, coords: Coords, cell: &Cell, frame: &mut Frame, rng: &mut impl Rng) -> bool {
+        if !cell.kind.phase().is_movable() || !self.region.contains(coords) {
+            return false;
+        }
+        if !rng.gen_bool(self.strength.clamp(0.0, 1.0) as f64) {
+            return false;
+        }
+        let step = match self.step(rng) {
+            Some(step) => step,
+            None => return false,
+        };
+        let target_coords = Coords { x: coords.x + step.x, y: coords.y + step.y };
+        match game.try_get_cell(target_coords.x, target_coords.y) {
+            Some(target) if target.hardness < cell.hardness => {
+                frame.add_action(Action::Swap(coords, target_coords));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns whether `push` could push the cell with any outcome of its random draws.
+    pub fn can_push(&self, game: &Game, coords: Coords, cell: &Cell) -> bool {
+        if !cell.kind.phase().is_movable() || !self.region.contains(coords) || self.strength <= 0.0 {
+            return false;
+        }
+        self.steps().into_iter().any(|step| {
+            game.try_get_cell(coords.x + step.x, coords.y + step.y).is_some_and(|target| target.hardness < cell.hardness)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Cells;
+    use crate::mask::STAR_MASK;
+    use rand::rngs::StdRng;
+
+    const WHOLE_WORLD: Region = Region::Rect { x: 0, y: 0, width: 5, height: 5 };
+
+    fn world_with(kind: Cells, coords: Coords) -> Game {
+        let mut game = Game::with_seed(5, 5, 0);
+        game.set_cell(coords.x, coords.y, Cell::new(kind));
+        game
+    }
+
+    /// Pushes the cell once and applies the resulting frame, returns whether it was pushed.
+    fn push_once(field: &ForceField, game: &mut Game, coords: Coords) -> bool {
+        let cell = *game.get_cell(coords.x, coords.y);
+        let mut frame = Frame::new();
+        let pushed = field.push(game, coords, &cell, &mut frame, &mut StdRng::seed_from_u64(0));
+        game.apply_frame(&mut frame);
+        pushed
+    }
+
+    #[test]
+    fn full_strength_pushes_sand_in_its_direction() {
+        let start = Coords { x: 2, y: 2 };
+        for (direction, target) in [((1.0, 0.0), (3, 2)), ((0.0, -1.0), (2, 1)), ((-1.0, 1.0), (1, 3))] {
+            let field = ForceField::new(WHOLE_WORLD, direction, 1.0);
+            let mut game = world_with(Cells::Sand, start);
+            assert!(field.can_push(&game, start, game.get_cell(start.x, start.y)));
+            assert!(push_once(&field, &mut game, start), "pushing towards {:?}", direction);
+            assert_eq!(game.get_cell(target.0, target.1).kind, Cells::Sand);
+            assert_eq!(game.get_cell(start.x, start.y).kind, Cells::Air);
+        }
+    }
+
+    #[test]
+    fn walls_and_air_are_never_pushed() {
+        let start = Coords { x: 2, y: 2 };
+        let field = ForceField::new(WHOLE_WORLD, (1.0, 0.0), 1.0).with_turbulence(std::f32::consts::PI);
+        for kind in [Cells::Wall, Cells::Air] {
+            let mut game = world_with(kind, start);
+            assert!(!field.can_push(&game, start, game.get_cell(start.x, start.y)));
+            assert!(!push_once(&field, &mut game, start));
+            assert_eq!(game.get_cell(start.x, start.y).kind, kind);
+        }
+    }
+
+    #[test]
+    fn mask_regions_only_push_cells_under_the_mask() {
+        let mut mask = STAR_MASK;
+        mask.coords = Coords { x: 2, y: 2 };
+        let field = ForceField::new(Region::Mask(mask), (0.0, -1.0), 1.0);
+        for (coords, covered) in [((2, 2), true), ((1, 2), true), ((2, 3), true), ((1, 1), false), ((3, 3), false)] {
+            let coords = Coords { x: coords.0, y: coords.1 };
+            let mut game = world_with(Cells::Sand, coords);
+            assert_eq!(push_once(&field, &mut game, coords), covered, "sand at {:?}", coords);
+            assert_eq!(game.get_cell(coords.x, coords.y - 1).kind == Cells::Sand, covered);
+        }
+    }
+}
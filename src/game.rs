@@ -1,52 +1,158 @@
-use sfml::graphics::{RenderTarget, Shape, Transformable};
 use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
-use crate::mask;
+use crate::color::Color;
+use crate::force::ForceField;
+use crate::history::History;
+use crate::light::Lighting;
 
 pub struct Game {
     cells: Vec<Cell>,
+    /// Static backdrop drawn behind the cells, `Cells::Air` where there is none.
+    background: Vec<Cells>,
     width: u32,
     height: u32,
     /// How many cells will be randomly updated each iteration.
     pub iteration: u32,
+    /// Force fields pushing movable cells around, applied during `Cell::update`.
+    pub force_fields: Vec<ForceField>,
+    /// Undo/redo steps recorded by `apply_frame_recorded`.
+    pub history: History,
+    seed: u64,
+    tick: u64,
+    /// Resolves random conflicts, reseeded from the seed every tick.
+    rng: StdRng,
+    /// Whether `rng` was drawn from since it was last reseeded.
+    rng_used: bool,
+    /// Tick in which each cell was last changed, `u64::MAX` if never.
+    changed_at: Vec<u64>,
+    /// Number of cells changed during the current tick.
+    changed_count: usize,
+    /// Number of cells changed during the previous tick.
+    last_changed_count: usize,
+    /// Panics when a frame made only of swaps creates or destroys matter.
+    pub check_conservation: bool,
+    /// Light the world is drawn with, `None` draws every cell fully lit.
+    pub lighting: Option<Lighting>,
 }
 
+/// Random stream used for picking and updating cells.
+const UPDATE_STREAM: u64 = 0;
+/// Random stream used for resolving conflicts in frames.
+const CONFLICT_STREAM: u64 = 1;
 
 impl Game {
+    /// Creates an empty game with a random seed.
     pub fn new(width: u32, height: u32) -> Game {
+        Game::with_seed(width, height, rand::random())
+    }
+
+    /// Creates an empty game whose simulation is fully determined by the seed and the applied frames.
+    pub fn with_seed(width: u32, height: u32, seed: u64) -> Game {
+        Game::from_parts(width, height, vec![Cell::default(); (width * height) as usize], seed, 0)
+    }
+
+    /// Rebuilds a game from its raw state, cells are stored row by row.
+    pub(crate) fn from_parts(width: u32, height: u32, cells: Vec<Cell>, seed: u64, tick: u64) -> Game {
+        assert_eq!(cells.len(), (width * height) as usize, "cell count doesn't match the dimensions");
         Game {
-            cells: vec![Cell::default(); (width * height) as usize],
+            changed_at: vec![u64::MAX; cells.len()],
+            background: vec![Cells::Air; cells.len()],
+            cells,
             width,
             height,
             iteration: crate::ITERATON,
+            force_fields: Vec::new(),
+            history: History::default(),
+            seed,
+            tick,
+            rng: Self::seeded_rng(seed, tick, CONFLICT_STREAM),
+            rng_used: false,
+            changed_count: 0,
+            last_changed_count: 0,
+            check_conservation: false,
+            lighting: None,
         }
     }
 
-    pub fn draw(&self, rw: &mut sfml::graphics::RenderWindow) {
-        let cell_size = crate::CELL_SIZE;
-        let mut shape = sfml::graphics::RectangleShape::new();
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let cell = self.get_cell(x as i32, y as i32);
-                shape.set_size((cell_size as f32, cell_size as f32));
-                shape.set_position((x as f32 * cell_size as f32, y as f32 * cell_size as f32));
-                shape.set_fill_color(cell.color());
-                rw.draw(&shape);
-            }
-        }
+    /// Replaces the whole world, every cell counts as changed and the undo history is forgotten.
+    pub(crate) fn replace_cells(&mut self, width: u32, height: u32, cells: Vec<Cell>, background: Vec<Cells>) {
+        assert_eq!(cells.len(), (width * height) as usize, "cell count doesn't match the dimensions");
+        assert_eq!(background.len(), cells.len(), "background size doesn't match the cells");
+        self.changed_at = vec![self.tick; cells.len()];
+        self.changed_count = cells.len();
+        self.cells = cells;
+        self.background = background;
+        self.width = width;
+        self.height = height;
+        self.history.clear();
+    }
+
+    /// Returns the random generator for the given tick, streams are independent of each other.
+    ///
+    /// Seed, tick and stream are hashed one after another, so nearby seeds don't replay each other shifted by some ticks.
+    fn seeded_rng(seed: u64, tick: u64, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(splitmix64(splitmix64(splitmix64(seed) ^ tick) ^ stream))
     }
 
+    /// Advances the simulation by one tick, a world without cells only counts the tick.
     pub fn update(&mut self, frame: &mut Frame) {
-        let mut rng = rand::thread_rng();
-        for _ in 0..self.iteration {
+        let mut rng = Self::seeded_rng(self.seed, self.tick, UPDATE_STREAM);
+        let iteration = if self.cells.is_empty() { 0 } else { self.iteration };
+        for _ in 0..iteration {
             let x = rng.gen_range(0..self.width);
             let y = rng.gen_range(0..self.height);
             let cell = self.get_cell(x as i32, y as i32);
             let (x, y) = (x as i32, y as i32);
-            Cell::update(self, Coords { x, y }, cell, frame);
+            Cell::update(self, Coords { x, y }, cell, frame, &mut rng);
             self.apply_frame(frame);
         }
-        
+        self.tick += 1;
+        self.rng = Self::seeded_rng(self.seed, self.tick, CONFLICT_STREAM);
+        self.rng_used = false;
+        self.last_changed_count = std::mem::take(&mut self.changed_count);
+    }
+
+    /// Updates the game until no cell changed for `stable_ticks` ticks in a row and `is_at_rest` confirms it,
+    /// or until `max_ticks` ticks have passed.
+    ///
+    /// Quiet ticks alone don't prove anything, every tick only updates `iteration` random cells.
+    pub fn settle(&mut self, stable_ticks: u64, max_ticks: u64) -> Settle {
+        self.settle_with(stable_ticks, max_ticks, |_| {})
+    }
+
+    /// Same as `settle`, calling `after_tick` after every update.
+    pub fn settle_with(&mut self, stable_ticks: u64, max_ticks: u64, mut after_tick: impl FnMut(&Game)) -> Settle {
+        let mut frame = Frame::new();
+        let mut stable = 0;
+        let mut ticks = 0;
+        loop {
+            if stable >= stable_ticks {
+                if self.is_at_rest() {
+                    return Settle { ticks, converged: true };
+                }
+                stable = 0;
+            }
+            if ticks >= max_ticks {
+                return Settle { ticks, converged: false };
+            }
+            self.update(&mut frame);
+            after_tick(self);
+            ticks += 1;
+            if self.changed_last_tick() == 0 {
+                stable += 1;
+            } else {
+                stable = 0;
+            }
+        }
+    }
+
+    /// Returns whether no cell could move, whichever cells get updated and whatever their random draws.
+    pub fn is_at_rest(&self) -> bool {
+        (0..self.height as i32)
+            .all(|y| (0..self.width as i32).all(|x| !Cell::can_move(self, Coords { x, y }, self.get_cell(x, y))))
     }
 
     pub fn width(&self) -> u32 {
@@ -57,6 +163,33 @@ impl Game {
         self.height
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Replaces the seed, the simulation from now on is determined by the new one.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Self::seeded_rng(seed, self.tick, CONFLICT_STREAM);
+        self.rng_used = false;
+    }
+
+    /// Returns whether a frame with random conflicts was resolved since the start of the tick,
+    /// a game rebuilt from the seed and the tick would resolve the next ones differently.
+    pub(crate) fn is_mid_tick(&self) -> bool {
+        self.rng_used
+    }
+
+    /// Returns how many times the game has been updated.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns every cell of the game, row by row.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
     /// Returns the cell at the given coordinates, if it exists.
     pub fn try_get_cell(&self, x: i32, y: i32) -> Option<&Cell> {
         if self.cell_exists(x, y){
@@ -76,15 +209,74 @@ impl Game {
     /// Sets the cell at the given coordinates.
     pub fn try_set_cell(&mut self, x: i32, y: i32, cell: Cell) {
         if self.cell_exists(x, y) {
-            let (x, y) = (x as u32, y as u32);
-            self.cells[(y * self.width + x) as usize] = cell;
+            self.set_cell(x, y, cell);
         }
     }
 
     /// Sets the cell at the given coordinates or panics if it doesn't exist.
     pub fn set_cell(&mut self, x: i32, y: i32, cell: Cell) {
         let (x, y) = (x as u32, y as u32);
-        self.cells[(y * self.width + x) as usize] = cell;
+        let index = (y * self.width + x) as usize;
+        if self.cells[index] == cell {
+            return;
+        }
+        self.cells[index] = cell;
+        if self.changed_at[index] != self.tick {
+            self.changed_at[index] = self.tick;
+            self.changed_count += 1;
+        }
+    }
+
+    /// Returns the background layer, row by row.
+    pub fn background(&self) -> &[Cells] {
+        &self.background
+    }
+
+    /// Replaces the background layer while loading, before any cell could have been drawn.
+    pub(crate) fn set_background_layer(&mut self, background: Vec<Cells>) {
+        assert_eq!(background.len(), self.cells.len(), "background size doesn't match the cells");
+        self.background = background;
+    }
+
+    pub fn try_get_background(&self, x: i32, y: i32) -> Option<Cells> {
+        if self.cell_exists(x, y) {
+            Some(self.get_background(x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the background material at the given coordinates or panics if it doesn't exist.
+    pub fn get_background(&self, x: i32, y: i32) -> Cells {
+        let (x, y) = (x as u32, y as u32);
+        self.background[(y * self.width + x) as usize]
+    }
+
+    /// Sets the background at the given coordinates or panics if it doesn't exist.
+    ///
+    /// The cell in front of it counts as changed, so renderers repaint it.
+    pub fn set_background(&mut self, x: i32, y: i32, kind: Cells) {
+        let (x, y) = (x as u32, y as u32);
+        let index = (y * self.width + x) as usize;
+        if self.background[index] == kind {
+            return;
+        }
+        self.background[index] = kind;
+        if self.changed_at[index] != self.tick {
+            self.changed_at[index] = self.tick;
+            self.changed_count += 1;
+        }
+    }
+
+    /// Returns the tick in which the cell was last changed, if it ever was.
+    pub fn changed_at(&self, x: i32, y: i32) -> Option<u64> {
+        let (x, y) = (x as u32, y as u32);
+        Some(self.changed_at[(y * self.width + x) as usize]).filter(|tick| *tick != u64::MAX)
+    }
+
+    /// Returns how many cells changed during the last update, including frames applied before it.
+    pub fn changed_last_tick(&self) -> usize {
+        self.last_changed_count
     }
     
     pub fn cell_exists(&self, x: i32, y: i32) -> bool {
@@ -103,38 +295,203 @@ impl Game {
     }
 
     /// Exhaustively applies the actions in the frame to the game.
-    pub fn apply_frame(&mut self, frame: &mut Frame) {
+    ///
+    /// Actions targeting the same cell are resolved by the frame's `ConflictPolicy`,
+    /// actions reaching outside of the world are ignored.
+    /// Returns the number of actions that were dropped because of a conflict.
+    pub fn apply_frame(&mut self, frame: &mut Frame) -> usize {
+        if self.check_conservation {
+            return self.apply_frame_checked(frame);
+        }
+        self.apply_frame_inner(frame, None)
+    }
+
+    /// Applies the frame and panics if it was made only of swaps and didn't conserve matter.
+    fn apply_frame_checked(&mut self, frame: &mut Frame) -> usize {
+        let mut actions = Vec::with_capacity(frame.len());
         while let Some(action) = frame.poll() {
-            match action {
-                Action::Cell(coords, cell_action) => {
-                    match cell_action {
-                        CellAction::Set(cell) => self.try_set_cell(coords.x, coords.y, cell),
-                        CellAction::Clear => self.set_cell(coords.x, coords.y, Cell::default()),
-                        CellAction::SetKind(kind) => self.set_cell(coords.x, coords.y, Cell::new(kind)),
-                        CellAction::SetColorMode(color_mode) => {
-                            let mut cell = self.get_cell(coords.x, coords.y).clone();
-                            cell.color_mode = color_mode;
-                            self.set_cell(coords.x, coords.y, cell);
-                        }
-                        CellAction::SetHardness(hardness) => {
-                            let mut cell = self.get_cell(coords.x, coords.y).clone();
-                            cell.hardness = hardness;
-                            self.set_cell(coords.x, coords.y, cell);
-                        }
+            actions.push(action);
+        }
+        let swaps_only = actions.iter().all(|action| matches!(action, Action::Swap(..)));
+        let touched: HashSet<Coords> = actions
+            .iter()
+            .flat_map(Action::targets)
+            .filter(|coords| self.cell_exists(coords.x, coords.y))
+            .collect();
+        let count_kinds = |game: &Game| {
+            let mut counts = [0usize; Cells::ALL.len()];
+            for coords in &touched {
+                counts[game.get_cell(coords.x, coords.y).kind.index()] += 1;
+            }
+            counts
+        };
+        let before = count_kinds(self);
+        let mut checked = Frame::with_policy(frame.policy());
+        for action in actions {
+            checked.add_action(action);
+        }
+        let dropped = self.apply_frame_inner(&mut checked, None);
+        if swaps_only {
+            assert_eq!(before, count_kinds(self), "frame of swaps didn't conserve matter at tick {}", self.tick);
+        }
+        dropped
+    }
+
+    /// Applies the frame like `apply_frame` and records it as one undo step in the history.
+    pub fn apply_frame_recorded(&mut self, frame: &mut Frame) -> usize {
+        let mut inverse = Vec::new();
+        let dropped = self.apply_frame_inner(frame, Some(&mut inverse));
+        if !inverse.is_empty() {
+            self.history.push(inverse);
+        }
+        dropped
+    }
+
+    /// Reverts the last recorded frame. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let inverse = match self.history.pop_undo() {
+            Some(inverse) => inverse,
+            None => return false,
+        };
+        let mut redo = Vec::new();
+        self.apply_frame_inner(&mut History::to_frame(inverse), Some(&mut redo));
+        self.history.push_redo(redo);
+        true
+    }
+
+    /// Applies the last undone frame again. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let inverse = match self.history.pop_redo() {
+            Some(inverse) => inverse,
+            None => return false,
+        };
+        let mut undo = Vec::new();
+        self.apply_frame_inner(&mut History::to_frame(inverse), Some(&mut undo));
+        self.history.push_undo(undo);
+        true
+    }
+
+    /// Applies the frame and, if given, records the previous state of every touched cell.
+    fn apply_frame_inner(&mut self, frame: &mut Frame, mut inverse: Option<&mut Vec<Action>>) -> usize {
+        let policy = frame.policy();
+        if policy == ConflictPolicy::Overwrite || frame.len() < 2 {
+            while let Some(action) = frame.poll() {
+                self.apply_action(action, inverse.as_deref_mut());
+            }
+            return 0;
+        }
+        let mut actions = Vec::with_capacity(frame.len());
+        while let Some(action) = frame.poll() {
+            actions.push(action);
+        }
+        let total = actions.len();
+        self.rng_used |= policy == ConflictPolicy::Random;
+        let actions = Self::resolve_conflicts(actions, policy, &mut self.rng);
+        let dropped = total - actions.len();
+        for action in actions {
+            self.apply_action(action, inverse.as_deref_mut());
+        }
+        dropped
+    }
+
+    /// Filters out conflicting actions according to the policy.
+    ///
+    /// The actions that are left touch each cell at most once, so their order doesn't matter.
+    fn resolve_conflicts(mut actions: Vec<Action>, policy: ConflictPolicy, rng: &mut StdRng) -> Vec<Action> {
+        match policy {
+            ConflictPolicy::Overwrite => actions,
+            ConflictPolicy::FirstWins | ConflictPolicy::Random => {
+                if policy == ConflictPolicy::Random {
+                    actions.shuffle(rng);
+                }
+                let mut claimed = HashSet::new();
+                actions.retain(|action| {
+                    let layer = action.layer();
+                    if action.targets().any(|coords| claimed.contains(&(layer, coords))) {
+                        return false;
+                    }
+                    claimed.extend(action.targets().map(|coords| (layer, coords)));
+                    true
+                });
+                actions
+            }
+            ConflictPolicy::Reject => {
+                let mut uses: HashMap<(Layer, Coords), u32> = HashMap::new();
+                for action in &actions {
+                    for coords in action.targets() {
+                        *uses.entry((action.layer(), coords)).or_default() += 1;
                     }
                 }
-                Action::Swap(coords1, coords2) => {
-                    let cell1 = self.get_cell(coords1.x, coords1.y).clone();
-                    let cell2 = self.get_cell(coords2.x, coords2.y).clone();
-                    self.set_cell(coords1.x, coords1.y, cell2);
-                    self.set_cell(coords2.x, coords2.y, cell1);
+                actions.retain(|action| action.targets().all(|coords| uses[&(action.layer(), coords)] == 1));
+                actions
+            }
+        }
+    }
+
+    fn apply_action(&mut self, action: Action, inverse: Option<&mut Vec<Action>>) {
+        if !action.targets().all(|coords| self.cell_exists(coords.x, coords.y)) {
+            return;
+        }
+        if let Some(inverse) = inverse {
+            for coords in action.targets() {
+                inverse.push(match action.layer() {
+                    Layer::Foreground => Action::Cell(coords, CellAction::Set(*self.get_cell(coords.x, coords.y))),
+                    Layer::Background => {
+                        Action::Background(coords, BackgroundAction::Set(self.get_background(coords.x, coords.y)))
+                    }
+                });
+            }
+        }
+        match action {
+            Action::Cell(coords, cell_action) => {
+                match cell_action {
+                    CellAction::Set(cell) => self.try_set_cell(coords.x, coords.y, cell),
+                    CellAction::Clear => self.set_cell(coords.x, coords.y, Cell::default()),
+                    CellAction::SetKind(kind) => self.set_cell(coords.x, coords.y, Cell::new(kind)),
+                    CellAction::SetColorMode(color_mode) => {
+                        let mut cell = *self.get_cell(coords.x, coords.y);
+                        cell.color_mode = color_mode;
+                        self.set_cell(coords.x, coords.y, cell);
+                    }
+                    CellAction::SetHardness(hardness) => {
+                        let mut cell = *self.get_cell(coords.x, coords.y);
+                        cell.hardness = hardness;
+                        self.set_cell(coords.x, coords.y, cell);
+                    }
                 }
             }
+            Action::Swap(coords1, coords2) => {
+                let cell1 = *self.get_cell(coords1.x, coords1.y);
+                let cell2 = *self.get_cell(coords2.x, coords2.y);
+                self.set_cell(coords1.x, coords1.y, cell2);
+                self.set_cell(coords2.x, coords2.y, cell1);
+            }
+            Action::Background(coords, background_action) => match background_action {
+                BackgroundAction::Set(kind) => self.set_background(coords.x, coords.y, kind),
+                BackgroundAction::Clear => self.set_background(coords.x, coords.y, Cells::Air),
+            },
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// One step of the SplitMix64 generator, a cheap hash spreading every input bit over the whole output.
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Result of `Game::settle`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Settle {
+    /// How many ticks were run.
+    pub ticks: u64,
+    /// True if the world came to rest before the tick limit.
+    pub converged: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Cell {
     pub kind: Cells,
     pub color_mode: ColorMode,
@@ -159,24 +516,77 @@ impl Default for Cell {
 }
 
 impl Cell {
-    pub fn color(&self) -> sfml::graphics::Color {
+    pub fn color(&self) -> Color {
         match self.color_mode {
             ColorMode::Static(color) => color,
             ColorMode::Dynamic => self.kind.color(),
         }
     }
 
-    pub fn update(game: &Game, coords: Coords, cell: &Cell, frame: &mut Frame) {
+    /// Returns where a liquid moves when flowing towards the side, `None` if it rests there.
+    ///
+    /// It falls straight down, else down to the side, else along its row, but only if the row leads to a drop
+    /// before anything stops it, so puddles come to rest once they're flat. It only ever moves into something lighter.
+    fn flow_target(game: &Game, coords: Coords, cell: &Cell, side: i32) -> Option<Coords> {
+        let lighter = |x: i32, y: i32| game.try_get_cell(x, y).is_some_and(|other| other.kind.hardness() < cell.kind.hardness());
+        if lighter(coords.x, coords.y + 1) {
+            return Some(Coords { x: coords.x, y: coords.y + 1 });
+        }
+        if lighter(coords.x + side, coords.y + 1) {
+            return Some(Coords { x: coords.x + side, y: coords.y + 1 });
+        }
+        let mut x = coords.x + side;
+        while lighter(x, coords.y) {
+            if lighter(x, coords.y + 1) {
+                return Some(Coords { x: coords.x + side, y: coords.y });
+            }
+            x += side;
+        }
+        None
+    }
+
+    /// Returns whether `update` could move the cell with any outcome of its random draws.
+    ///
+    /// Mirrors the rules of `update`, keep both in sync.
+    pub fn can_move(game: &Game, coords: Coords, cell: &Cell) -> bool {
+        if cell.kind.phase().is_movable() && game.force_fields.iter().any(|field| field.can_push(game, coords, cell)) {
+            return true;
+        }
+        match cell.kind {
+            Cells::Air => false,
+            Cells::Wall => false,
+            Cells::Lava => [1, -1].into_iter().any(|side| Self::flow_target(game, coords, cell, side).is_some()),
+            // the diagonal checks of the sand rule compare the neighbour with itself, so sand only falls straight down
+            Cells::Sand => game
+                .try_get_cell(coords.x, coords.y + 1)
+                .is_some_and(|below| below.kind.hardness() < cell.kind.hardness()),
+        }
+    }
+
+    pub fn update(game: &Game, coords: Coords, cell: &Cell, frame: &mut Frame, rng: &mut impl Rng) {
+        if cell.kind.phase().is_movable() {
+            for field in &game.force_fields {
+                if field.push(game, coords, cell, frame, rng) {
+                    return;
+                }
+            }
+        }
         match cell.kind {
             Cells::Air => (),
             Cells::Wall => (),
+            Cells::Lava => {
+                let side = if rng.gen_bool(0.5) { 1 } else { -1 };
+                let target = Self::flow_target(game, coords, cell, side).or_else(|| Self::flow_target(game, coords, cell, -side));
+                if let Some(target) = target {
+                    frame.add_action(Action::Swap(coords, target));
+                }
+            }
             Cells::Sand => {
                 let below_coords = Coords { x: coords.x, y: coords.y + 1 };
                 if let Some(below_cell) = game.try_get_cell(below_coords.x, below_coords.y) {
                     if below_cell.kind.hardness() < cell.kind.hardness() {
                         frame.add_action(Action::Swap(coords, below_coords));
                     } else {
-                        let mut rng = rand::thread_rng();
                         let direction = rng.gen_range(0..2);
                         match direction {
                             0 => {
@@ -213,22 +623,42 @@ pub enum Cells {
     Air,
     Wall,
     Sand,
+    Lava,
 }
 
 impl Cells {
-    pub fn color(&self) -> sfml::graphics::Color {
+    /// Every material, in a stable order used by the file formats.
+    pub const ALL: [Cells; 4] = [Cells::Air, Cells::Wall, Cells::Sand, Cells::Lava];
+
+    /// Returns the position of the material in `Cells::ALL`.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn from_index(index: usize) -> Option<Cells> {
+        Cells::ALL.get(index).copied()
+    }
+
+    pub fn color(&self) -> Color {
         match self {
-            Cells::Air => sfml::graphics::Color::WHITE,
-            Cells::Wall => sfml::graphics::Color::BLACK,
-            Cells::Sand => sfml::graphics::Color::YELLOW,
+            Cells::Air => Color::WHITE,
+            Cells::Wall => Color::BLACK,
+            Cells::Sand => Color::YELLOW,
+            Cells::Lava => Color::rgb(255, 80, 0),
         }
     }
 
+    /// Colour of the material as a backdrop, darker so it reads as being behind the cells.
+    pub fn background_color(&self) -> Color {
+        self.color().blend(Color::rgba(0, 0, 0, 128))
+    }
+
     pub fn color_mode(&self) -> ColorMode {
         match self {
             Cells::Air => ColorMode::Static(self.color()),
             Cells::Wall => ColorMode::Static(self.color()),
             Cells::Sand => ColorMode::Static(self.color()),
+            Cells::Lava => ColorMode::Static(self.color()),
         }
     }
 
@@ -237,17 +667,73 @@ impl Cells {
             Cells::Air => 2,
             Cells::Wall => 100,
             Cells::Sand => 100,
+            Cells::Lava => 50,
+        }
+    }
+
+    /// Mass of a single cell of the material, air weighs nothing.
+    pub fn mass(&self) -> u32 {
+        match self {
+            Cells::Air => 0,
+            Cells::Wall => 1,
+            Cells::Sand => 1,
+            Cells::Lava => 1,
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self {
+            Cells::Air => Phase::Gas,
+            Cells::Wall => Phase::Solid,
+            Cells::Sand => Phase::Powder,
+            Cells::Lava => Phase::Liquid,
+        }
+    }
+
+    /// Light given off by the material, 0 for none.
+    pub fn emission(&self) -> u8 {
+        match self {
+            Cells::Air => 0,
+            Cells::Wall => 0,
+            Cells::Sand => 0,
+            Cells::Lava => 255,
+        }
+    }
+
+    /// Light lost when passing through a cell of the material, 255 blocks it completely.
+    pub fn opacity(&self) -> u8 {
+        match self {
+            Cells::Air => 8,
+            Cells::Wall => 255,
+            Cells::Sand => 96,
+            Cells::Lava => 32,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Physical state of a material, decides how it reacts to forces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Solid,
+    Powder,
+    Liquid,
+    Gas,
+}
+
+impl Phase {
+    /// Returns true for phases that can be pushed around by force fields.
+    pub fn is_movable(&self) -> bool {
+        !matches!(self, Phase::Solid)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ColorMode {
-    Static(sfml::graphics::Color),
+    Static(Color),
     Dynamic,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Coords {
     pub x: i32,
     pub y: i32,
@@ -265,7 +751,7 @@ pub struct Mask {
 
 impl Mask {
     pub const fn new(data: &'static [bool], stride: u32, center: Coords) -> Option<Mask> {
-        if data.len() as u32 % stride != 0 {
+        if !(data.len() as u32).is_multiple_of(stride) {
             return None;
         }
         Some(Mask { data, stride, center, coords: Coords { x: 0, y: 0 } })
@@ -274,6 +760,26 @@ impl Mask {
     pub fn get(&self, x: u32, y: u32) -> bool {
         self.data[(y * self.stride + x) as usize]
     }
+
+    /// Returns the world coordinates of every cell covered by the mask, placed at its coords.
+    pub fn iter_coords(&self) -> impl Iterator<Item = Coords> + '_ {
+        let stride = self.stride.max(1) as usize;
+        self.data.iter().enumerate().filter(|(_, set)| **set).map(move |(index, _)| Coords {
+            x: self.coords.x - self.center.x + (index % stride) as i32,
+            y: self.coords.y - self.center.y + (index / stride) as i32,
+        })
+    }
+
+    /// Returns true if the mask, placed at its coords, covers the given world coordinates.
+    pub fn contains(&self, coords: Coords) -> bool {
+        if self.stride == 0 {
+            return false;
+        }
+        let x = coords.x - self.coords.x + self.center.x;
+        let y = coords.y - self.coords.y + self.center.y;
+        let height = self.data.len() as i32 / self.stride as i32;
+        x >= 0 && y >= 0 && x < self.stride as i32 && y < height && self.get(x as u32, y as u32)
+    }
 }
 
 impl Default for Mask {
@@ -333,19 +839,126 @@ impl<'a> Iterator for MaskedCellIterator<'a> {
     }
 }
 
+/// Batch of actions applied to the game at once.
+///
+/// Actions are polled by priority, highest first. Actions with the same priority
+/// are polled in the frame's `FrameOrder`.
+#[derive(Clone, Default)]
 pub struct Frame {
-    actions: Vec<Action>,
+    actions: BinaryHeap<QueuedAction>,
+    policy: ConflictPolicy,
+    order: FrameOrder,
+    next_sequence: u64,
 }
 
 impl Frame {
     pub fn new() -> Frame {
+        Frame::default()
+    }
+
+    pub fn with_policy(policy: ConflictPolicy) -> Frame {
+        Frame {
+            policy,
+            ..Frame::default()
+        }
+    }
+
+    pub fn with_order(order: FrameOrder) -> Frame {
         Frame {
-            actions: Vec::new(),
+            order,
+            ..Frame::default()
+        }
+    }
+
+    pub fn policy(&self) -> ConflictPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: ConflictPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn order(&self) -> FrameOrder {
+        self.order
+    }
+
+    /// Changes the order of actions with the same priority, including the ones already added.
+    pub fn set_order(&mut self, order: FrameOrder) {
+        if self.order == order {
+            return;
         }
+        self.order = order;
+        let actions = std::mem::take(&mut self.actions);
+        self.actions = actions
+            .into_iter()
+            .map(|queued| QueuedAction { key: order.key(queued.sequence), ..queued })
+            .collect();
     }
 
     pub fn add_action(&mut self, action: Action) {
-        self.actions.push(action);
+        self.add_action_with_priority(action, 0);
+    }
+
+    /// Adds an action that will be polled before every action with a lower priority.
+    pub fn add_action_with_priority(&mut self, action: Action, priority: i32) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.actions.push(QueuedAction {
+            priority,
+            key: self.order.key(sequence),
+            sequence,
+            action,
+        });
+    }
+
+    /// Adds the cell action for every cell covered by the mask.
+    pub fn add_mask(&mut self, mask: &Mask, cell_action: CellAction) {
+        for coords in mask.iter_coords() {
+            self.add_action(Action::Cell(coords, cell_action));
+        }
+    }
+
+    /// Adds the cell action for every cell in the rectangle.
+    pub fn add_rect(&mut self, x: i32, y: i32, width: u32, height: u32, cell_action: CellAction) {
+        for j in y..y + height as i32 {
+            for i in x..x + width as i32 {
+                self.add_action(Action::Cell(Coords { x: i, y: j }, cell_action));
+            }
+        }
+    }
+
+    /// Adds the cell action for every cell on the line between `from` and `to`, both included.
+    pub fn add_line(&mut self, from: Coords, to: Coords, cell_action: CellAction) {
+        let dx = (to.x - from.x).abs();
+        let dy = -(to.y - from.y).abs();
+        let sx = if from.x < to.x { 1 } else { -1 };
+        let sy = if from.y < to.y { 1 } else { -1 };
+        let mut error = dx + dy;
+        let mut current = from;
+        loop {
+            self.add_action(Action::Cell(current, cell_action));
+            if current == to {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                current.x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                current.y += sy;
+            }
+        }
+    }
+
+    /// Moves every action from the other frame into this one, keeping their priorities.
+    ///
+    /// Merged actions are ordered as if they were added after the actions already in this frame.
+    pub fn merge(&mut self, mut other: Frame) {
+        while let Some(queued) = other.actions.pop() {
+            self.add_action_with_priority(queued.action, queued.priority);
+        }
     }
 
     pub fn clear(&mut self) {
@@ -361,19 +974,461 @@ impl Frame {
     }
 
     pub fn poll(&mut self) -> Option<Action> {
-        self.actions.pop()
+        self.actions.pop().map(|queued| queued.action)
+    }
+}
+
+/// Order in which actions with the same priority are polled from a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FrameOrder {
+    /// First added, first polled.
+    #[default]
+    Fifo,
+    /// Last added, first polled.
+    Lifo,
+}
+
+impl FrameOrder {
+    /// Returns the heap key for the nth added action, bigger keys are polled first.
+    fn key(&self, sequence: u64) -> u64 {
+        match self {
+            FrameOrder::Fifo => u64::MAX - sequence,
+            FrameOrder::Lifo => sequence,
+        }
     }
 }
 
+#[derive(Clone)]
+struct QueuedAction {
+    priority: i32,
+    key: u64,
+    sequence: u64,
+    action: Action,
+}
+
+impl PartialEq for QueuedAction {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedAction {}
+
+impl PartialOrd for QueuedAction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedAction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then(self.key.cmp(&other.key))
+    }
+}
+
+/// Decides what happens when several actions in one frame target the same cell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Every action is applied and later ones overwrite earlier ones.
+    ///
+    /// Two swaps into the same empty cell both apply, the later one silently moving the first grain elsewhere.
+    Overwrite,
+    /// The action polled first wins, the others are dropped.
+    #[default]
+    FirstWins,
+    /// A random action out of the conflicting ones wins.
+    Random,
+    /// Every action touching a contested cell is dropped.
+    Reject,
+}
+
+#[derive(Debug, Copy, Clone)]
 pub enum Action {
     Cell(Coords, CellAction),
     Swap(Coords, Coords),
+    Background(Coords, BackgroundAction),
+}
+
+impl Action {
+    /// Returns the coordinates of every cell the action changes.
+    pub fn targets(&self) -> impl Iterator<Item = Coords> {
+        let (first, second) = match *self {
+            Action::Cell(coords, _) | Action::Background(coords, _) => (coords, None),
+            Action::Swap(a, b) => (a, if a == b { None } else { Some(b) }),
+        };
+        std::iter::once(first).chain(second)
+    }
+
+    /// Returns the layer the action changes, actions on different layers never conflict.
+    pub fn layer(&self) -> Layer {
+        match self {
+            Action::Cell(..) | Action::Swap(..) => Layer::Foreground,
+            Action::Background(..) => Layer::Background,
+        }
+    }
+}
+
+/// Layer of the world an action changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Layer {
+    /// The simulated cells.
+    Foreground,
+    /// The static backdrop behind them.
+    Background,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum BackgroundAction {
+    Set(Cells),
+    Clear,
 }
 
+#[derive(Debug, Copy, Clone)]
 pub enum CellAction {
     Set(Cell),
     SetKind(Cells),
     SetColorMode(ColorMode),
     SetHardness(i32),
     Clear,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: Coords = Coords { x: 0, y: 0 };
+    const SECOND: Coords = Coords { x: 2, y: 0 };
+    const TARGET: Coords = Coords { x: 1, y: 1 };
+
+    /// Two grains of sand above an empty cell, both swapping into it.
+    fn two_swaps_into_one_cell(seed: u64, policy: ConflictPolicy) -> (Game, usize) {
+        let mut game = Game::with_seed(3, 2, seed);
+        game.set_cell(FIRST.x, FIRST.y, Cell::new(Cells::Sand));
+        game.set_cell(SECOND.x, SECOND.y, Cell::new(Cells::Sand));
+        let mut frame = Frame::with_policy(policy);
+        frame.add_action(Action::Swap(FIRST, TARGET));
+        frame.add_action(Action::Swap(SECOND, TARGET));
+        let dropped = game.apply_frame(&mut frame);
+        (game, dropped)
+    }
+
+    fn kind_at(game: &Game, coords: Coords) -> Cells {
+        game.get_cell(coords.x, coords.y).kind
+    }
+
+    fn sand_count(game: &Game) -> usize {
+        game.cells().iter().filter(|cell| cell.kind == Cells::Sand).count()
+    }
+
+    #[test]
+    fn seeds_dont_replay_each_other_shifted_by_ticks() {
+        let first = |seed: u64, tick: u64| Game::seeded_rng(seed, tick, UPDATE_STREAM).gen::<u64>();
+        for seed in 0..64 {
+            for shift in 1..8 {
+                assert_ne!(first(seed ^ (shift << 1), 0), first(seed, shift));
+                assert_ne!(first(seed + 1, 0), first(seed, shift));
+            }
+            assert_ne!(first(seed, 0), Game::seeded_rng(seed, 0, CONFLICT_STREAM).gen::<u64>());
+        }
+    }
+
+    #[test]
+    fn first_wins_keeps_the_first_swap() {
+        let (game, dropped) = two_swaps_into_one_cell(0, ConflictPolicy::FirstWins);
+        assert_eq!(dropped, 1);
+        assert_eq!(sand_count(&game), 2);
+        assert_eq!(kind_at(&game, FIRST), Cells::Air);
+        assert_eq!(kind_at(&game, SECOND), Cells::Sand);
+        assert_eq!(kind_at(&game, TARGET), Cells::Sand);
+    }
+
+    #[test]
+    fn random_keeps_one_swap_picked_by_the_seed() {
+        let mut winners = HashSet::new();
+        for seed in 0..32 {
+            let (game, dropped) = two_swaps_into_one_cell(seed, ConflictPolicy::Random);
+            assert_eq!(dropped, 1);
+            assert_eq!(sand_count(&game), 2);
+            assert_eq!(kind_at(&game, TARGET), Cells::Sand);
+            let winner = if kind_at(&game, FIRST) == Cells::Air { FIRST } else { SECOND };
+            assert_eq!(kind_at(&game, if winner == FIRST { SECOND } else { FIRST }), Cells::Sand);
+            // the same seed always picks the same winner
+            let (again, _) = two_swaps_into_one_cell(seed, ConflictPolicy::Random);
+            assert_eq!(again.cells(), game.cells());
+            winners.insert(winner);
+        }
+        assert_eq!(winners.len(), 2, "every seed picked the same winner");
+    }
+
+    #[test]
+    fn reject_drops_every_conflicting_swap() {
+        let (game, dropped) = two_swaps_into_one_cell(0, ConflictPolicy::Reject);
+        assert_eq!(dropped, 2);
+        assert_eq!(kind_at(&game, FIRST), Cells::Sand);
+        assert_eq!(kind_at(&game, SECOND), Cells::Sand);
+        assert_eq!(kind_at(&game, TARGET), Cells::Air);
+    }
+
+    #[test]
+    fn reject_keeps_actions_without_conflicts() {
+        let mut game = Game::with_seed(3, 2, 0);
+        game.set_cell(FIRST.x, FIRST.y, Cell::new(Cells::Sand));
+        let mut frame = Frame::with_policy(ConflictPolicy::Reject);
+        frame.add_action(Action::Swap(FIRST, TARGET));
+        frame.add_action(Action::Cell(SECOND, CellAction::SetKind(Cells::Wall)));
+        assert_eq!(game.apply_frame(&mut frame), 0);
+        assert_eq!(kind_at(&game, TARGET), Cells::Sand);
+        assert_eq!(kind_at(&game, SECOND), Cells::Wall);
+    }
+
+    #[test]
+    fn overwrite_applies_both_swaps() {
+        let (game, dropped) = two_swaps_into_one_cell(0, ConflictPolicy::Overwrite);
+        assert_eq!(dropped, 0);
+        // the second swap pulls the first grain out of the target again, into the second grain's place
+        assert_eq!(kind_at(&game, FIRST), Cells::Air);
+        assert_eq!(kind_at(&game, SECOND), Cells::Sand);
+        assert_eq!(kind_at(&game, TARGET), Cells::Sand);
+    }
+
+    #[test]
+    fn overwrite_duplicates_matter_moved_with_set() {
+        // a grain moved by setting the target and clearing the source, while a later action writes the source again
+        let mut game = Game::with_seed(3, 2, 0);
+        game.set_cell(FIRST.x, FIRST.y, Cell::new(Cells::Sand));
+        let mut frame = Frame::with_policy(ConflictPolicy::Overwrite);
+        frame.add_action(Action::Cell(TARGET, CellAction::SetKind(Cells::Sand)));
+        frame.add_action(Action::Cell(FIRST, CellAction::Clear));
+        frame.add_action(Action::Cell(FIRST, CellAction::SetKind(Cells::Sand)));
+        assert_eq!(game.apply_frame(&mut frame), 0);
+        assert_eq!(sand_count(&game), 2);
+
+        let mut game = Game::with_seed(3, 2, 0);
+        game.set_cell(FIRST.x, FIRST.y, Cell::new(Cells::Sand));
+        let mut frame = Frame::with_policy(ConflictPolicy::FirstWins);
+        frame.add_action(Action::Cell(TARGET, CellAction::SetKind(Cells::Sand)));
+        frame.add_action(Action::Cell(FIRST, CellAction::Clear));
+        frame.add_action(Action::Cell(FIRST, CellAction::SetKind(Cells::Sand)));
+        assert_eq!(game.apply_frame(&mut frame), 1);
+        assert_eq!(sand_count(&game), 1);
+    }
+
+    fn kinds(game: &Game) -> Vec<Cells> {
+        game.cells().iter().map(|cell| cell.kind).collect()
+    }
+
+    fn set_kind(coords: Coords, kind: Cells) -> Action {
+        Action::Cell(coords, CellAction::SetKind(kind))
+    }
+
+    fn apply_recorded(game: &mut Game, actions: &[Action]) {
+        let mut frame = Frame::with_policy(ConflictPolicy::Overwrite);
+        for action in actions {
+            frame.add_action(*action);
+        }
+        game.apply_frame_recorded(&mut frame);
+    }
+
+    #[test]
+    fn undo_restores_and_redo_reapplies_a_frame() {
+        let mut game = Game::with_seed(3, 2, 0);
+        game.set_cell(0, 0, Cell::new(Cells::Sand));
+        let before = game.snapshot();
+        apply_recorded(&mut game, &[Action::Swap(FIRST, TARGET), set_kind(SECOND, Cells::Wall)]);
+        let after = game.snapshot();
+        assert!(game.history.can_undo());
+
+        assert!(game.undo());
+        assert_eq!(game.snapshot(), before);
+        assert!(!game.history.can_undo());
+        assert!(game.history.can_redo());
+
+        assert!(game.redo());
+        assert_eq!(game.snapshot(), after);
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn undo_restores_the_first_recorded_state_of_a_cell() {
+        let mut game = Game::with_seed(3, 2, 0);
+        apply_recorded(&mut game, &[set_kind(TARGET, Cells::Sand), set_kind(TARGET, Cells::Wall)]);
+        assert_eq!(kind_at(&game, TARGET), Cells::Wall);
+        assert!(game.undo());
+        assert_eq!(kind_at(&game, TARGET), Cells::Air);
+        assert!(game.redo());
+        assert_eq!(kind_at(&game, TARGET), Cells::Wall);
+    }
+
+    #[test]
+    fn history_limit_forgets_the_oldest_step() {
+        let mut game = Game::with_seed(3, 1, 0);
+        game.history = History::new(2);
+        for (x, kind) in [Cells::Sand, Cells::Wall, Cells::Lava].into_iter().enumerate() {
+            apply_recorded(&mut game, &[set_kind(Coords { x: x as i32, y: 0 }, kind)]);
+        }
+        assert!(game.undo());
+        assert!(game.undo());
+        assert!(!game.undo());
+        // the first step can't be undone anymore
+        assert_eq!(kinds(&game), [Cells::Sand, Cells::Air, Cells::Air]);
+    }
+
+    #[test]
+    fn new_step_clears_the_redo_stack() {
+        let mut game = Game::with_seed(3, 1, 0);
+        apply_recorded(&mut game, &[set_kind(Coords { x: 0, y: 0 }, Cells::Sand)]);
+        assert!(game.undo());
+        assert!(game.history.can_redo());
+        apply_recorded(&mut game, &[set_kind(Coords { x: 1, y: 0 }, Cells::Wall)]);
+        assert!(!game.history.can_redo());
+        assert!(!game.redo());
+        assert_eq!(kinds(&game), [Cells::Air, Cells::Wall, Cells::Air]);
+    }
+
+    #[test]
+    fn undo_restores_the_background() {
+        let mut game = Game::with_seed(3, 2, 0);
+        apply_recorded(&mut game, &[Action::Background(TARGET, BackgroundAction::Set(Cells::Wall))]);
+        assert!(game.undo());
+        assert_eq!(game.get_background(TARGET.x, TARGET.y), Cells::Air);
+    }
+
+    #[test]
+    fn swap_only_frames_pass_the_conservation_check() {
+        for policy in [ConflictPolicy::FirstWins, ConflictPolicy::Random, ConflictPolicy::Reject, ConflictPolicy::Overwrite] {
+            let (mut game, mut frame) = (Game::with_seed(3, 2, 0), Frame::with_policy(policy));
+            game.check_conservation = true;
+            game.set_cell(FIRST.x, FIRST.y, Cell::new(Cells::Sand));
+            game.set_cell(SECOND.x, SECOND.y, Cell::new(Cells::Lava));
+            frame.add_action(Action::Swap(FIRST, TARGET));
+            frame.add_action(Action::Swap(SECOND, TARGET));
+            frame.add_action(Action::Swap(Coords { x: 1, y: 0 }, Coords { x: 2, y: 1 }));
+            game.apply_frame(&mut frame);
+            assert_eq!((game.count(Cells::Sand), game.count(Cells::Lava)), (1, 1), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn frames_creating_matter_are_not_checked() {
+        let mut game = Game::with_seed(3, 2, 0);
+        game.check_conservation = true;
+        let mut frame = Frame::new();
+        frame.add_action(set_kind(FIRST, Cells::Sand));
+        frame.add_action(Action::Swap(FIRST, TARGET));
+        game.apply_frame(&mut frame);
+        assert_eq!(sand_count(&game), 1);
+    }
+
+    fn at(x: i32, y: i32) -> Coords {
+        Coords { x, y }
+    }
+
+    fn marker(x: i32) -> Action {
+        set_kind(at(x, 0), Cells::Sand)
+    }
+
+    /// Polls every action and returns the first cell each one targets.
+    fn polled(frame: &mut Frame) -> Vec<Coords> {
+        std::iter::from_fn(|| frame.poll()).map(|action| action.targets().next().unwrap()).collect()
+    }
+
+    fn xs(frame: &mut Frame) -> Vec<i32> {
+        polled(frame).into_iter().map(|coords| coords.x).collect()
+    }
+
+    #[test]
+    fn fifo_polls_in_insertion_order_and_lifo_in_reverse() {
+        let mut fifo = Frame::new();
+        let mut lifo = Frame::with_order(FrameOrder::Lifo);
+        for x in 0..4 {
+            fifo.add_action(marker(x));
+            lifo.add_action(marker(x));
+        }
+        assert_eq!(xs(&mut fifo), [0, 1, 2, 3]);
+        assert_eq!(xs(&mut lifo), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn higher_priorities_are_polled_first() {
+        let mut frame = Frame::new();
+        frame.add_action_with_priority(marker(0), 0);
+        frame.add_action_with_priority(marker(1), 5);
+        frame.add_action_with_priority(marker(2), -1);
+        frame.add_action_with_priority(marker(3), 5);
+        assert_eq!(xs(&mut frame), [1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn set_order_rekeys_queued_actions() {
+        let mut frame = Frame::new();
+        for x in 0..3 {
+            frame.add_action(marker(x));
+        }
+        frame.add_action_with_priority(marker(9), 1);
+        frame.set_order(FrameOrder::Lifo);
+        frame.add_action(marker(3));
+        assert_eq!(xs(&mut frame), [9, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn merge_keeps_the_other_frames_order() {
+        for (order, expected) in [(FrameOrder::Fifo, [0, 1, 2, 3]), (FrameOrder::Lifo, [0, 1, 3, 2])] {
+            let mut frame = Frame::new();
+            frame.add_action(marker(0));
+            frame.add_action(marker(1));
+            let mut other = Frame::with_order(order);
+            other.add_action(marker(2));
+            other.add_action(marker(3));
+            frame.merge(other);
+            assert_eq!(xs(&mut frame), expected, "merging a {:?} frame", order);
+        }
+    }
+
+    #[test]
+    fn merge_keeps_priorities() {
+        let mut frame = Frame::new();
+        frame.add_action(marker(0));
+        let mut other = Frame::new();
+        other.add_action_with_priority(marker(1), 2);
+        frame.merge(other);
+        assert_eq!(xs(&mut frame), [1, 0]);
+    }
+
+    #[test]
+    fn add_rect_covers_the_rectangle_row_by_row() {
+        let mut frame = Frame::new();
+        frame.add_rect(1, 2, 2, 2, CellAction::Clear);
+        assert_eq!(polled(&mut frame), [at(1, 2), at(2, 2), at(1, 3), at(2, 3)]);
+        frame.add_rect(0, 0, 0, 3, CellAction::Clear);
+        assert!(frame.is_empty());
+    }
+
+    fn line(from: Coords, to: Coords) -> Vec<Coords> {
+        let mut frame = Frame::new();
+        frame.add_line(from, to, CellAction::Clear);
+        polled(&mut frame)
+    }
+
+    #[test]
+    fn add_line_includes_both_endpoints() {
+        assert_eq!(line(at(2, 2), at(2, 2)), [at(2, 2)]);
+        assert_eq!(line(at(0, 0), at(3, 0)), [at(0, 0), at(1, 0), at(2, 0), at(3, 0)]);
+        assert_eq!(line(at(0, 0), at(3, 1)), [at(0, 0), at(1, 0), at(2, 1), at(3, 1)]);
+    }
+
+    #[test]
+    fn add_line_handles_steep_and_negative_slopes() {
+        assert_eq!(line(at(0, 0), at(1, 3)), [at(0, 0), at(0, 1), at(1, 2), at(1, 3)]);
+        assert_eq!(line(at(3, 3), at(0, 0)), [at(3, 3), at(2, 2), at(1, 1), at(0, 0)]);
+        for (from, to) in [(at(5, 1), at(-2, 4)), (at(0, 6), at(2, -3)), (at(-1, -1), at(-4, 7))] {
+            let points = line(from, to);
+            assert_eq!(points.first(), Some(&from));
+            assert_eq!(points.last(), Some(&to));
+            // one cell per step along the longer axis, every cell touching the previous one
+            assert_eq!(points.len() as i32, (to.x - from.x).abs().max((to.y - from.y).abs()) + 1);
+            for pair in points.windows(2) {
+                assert!((pair[1].x - pair[0].x).abs() <= 1 && (pair[1].y - pair[0].y).abs() <= 1, "{:?}", pair);
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+
+use crate::game::{Action, ConflictPolicy, Frame};
+
+/// Bounded undo/redo stack.
+///
+/// Every step is a list of inverse actions, in the order the original actions were applied.
+/// Undoing a step applies them backwards, so the first recorded state of a cell wins.
+pub struct History {
+    undo: VecDeque<Vec<Action>>,
+    redo: Vec<Vec<Action>>,
+    /// Maximum number of undo steps kept, the oldest ones are forgotten first.
+    pub limit: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> History {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Records a new undo step and forgets everything that could be redone.
+    pub fn push(&mut self, inverse: Vec<Action>) {
+        self.redo.clear();
+        self.push_undo(inverse);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub(crate) fn push_undo(&mut self, inverse: Vec<Action>) {
+        if self.limit == 0 {
+            return;
+        }
+        while self.undo.len() >= self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(inverse);
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<Vec<Action>> {
+        self.undo.pop_back()
+    }
+
+    pub(crate) fn push_redo(&mut self, inverse: Vec<Action>) {
+        self.redo.push(inverse);
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<Vec<Action>> {
+        self.redo.pop()
+    }
+
+    /// Builds a frame applying the inverse actions backwards.
+    pub(crate) fn to_frame(inverse: Vec<Action>) -> Frame {
+        let mut frame = Frame::with_policy(ConflictPolicy::Overwrite);
+        for action in inverse.into_iter().rev() {
+            frame.add_action(action);
+        }
+        frame
+    }
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::new(100)
+    }
+}
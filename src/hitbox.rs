@@ -1,14 +1,16 @@
-use crate::{game::{Coords, Game}, CELL_SIZE};
+use crate::{camera::Area, contour::Contours, game::{Cells, Game}, CELL_SIZE};
 
 pub struct Hitbox {
     pub kind: Hitboxes,
     pub hardness: i32,
     pub border_collision: bool,
+    /// Whether the background layer blocks the hitbox too.
+    pub background_collision: bool,
 }
 
 impl Hitbox {
     pub fn new(kind: Hitboxes, hardness: i32) -> Self {
-        Self { kind, hardness, border_collision: true }
+        Self { kind, hardness, border_collision: true, background_collision: false }
     }
 
     pub fn collides(&self, game: &Game) -> bool {
@@ -21,8 +23,8 @@ impl Hitbox {
                 for i in x..x + width {
                     for j in y..y + height {
                         match game.try_get_cell(i, j) {
-                            Some(cell) => {
-                                if cell.hardness >= self.hardness {
+                            Some(_) => {
+                                if self.blocks(game, i, j) {
                                     return true;
                                 }
                             }
@@ -37,17 +39,34 @@ impl Hitbox {
                 false
             }
             Hitboxes::Circle { x, y, radius } => {
-                false
+                let outside = x - radius < 0.0 || y - radius < 0.0 || x + radius > game.width() as f32 || y + radius > game.height() as f32;
+                if outside && self.border_collision {
+                    return true;
+                }
+                // the circle collides with the smooth contours of the blocking cells, not with their squares
+                let left = (x - radius).floor() as i32 - 1;
+                let top = (y - radius).floor() as i32 - 1;
+                let size = (2.0 * radius).ceil() as u32 + 3;
+                let area = Area { x: left, y: top, width: size, height: size };
+                let contours = Contours::extract(game, area, |i, j| self.blocks(game, i, j));
+                contours.contains((x, y)) || contours.distance((x, y)).is_some_and(|distance| distance < radius)
             }
         }
     }
 
-    pub fn move_by(&self, game: &Game, x: f32, y: f32) -> MoveBy {
+    /// Returns whether the cell stops the hitbox.
+    fn blocks(&self, game: &Game, x: i32, y: i32) -> bool {
+        let background = game.get_background(x, y);
+        game.get_cell(x, y).hardness >= self.hardness
+            || self.background_collision && background != Cells::Air && background.hardness() >= self.hardness
+    }
+
+    pub fn move_by(&self, _game: &Game, x: f32, y: f32) -> MoveBy {
         match self.kind {
-            Hitboxes::Rect { x: hx, y: hy, width, height } => {
+            Hitboxes::Rect { .. } => {
                 MoveBy { x, y, stop_by: MoveStopBy::Natural }
             }
-            Hitboxes::Circle { x: hx, y: hy, radius } => {
+            Hitboxes::Circle { .. } => {
                 MoveBy { x, y, stop_by: MoveStopBy::Natural }
             }
         }
@@ -147,11 +166,11 @@ impl Hitbox {
             i += 1
         }
     
-        return MoveBy { x: start_x, y: start_y, stop_by: MoveStopBy::Natural }
+        MoveBy { x: start_x, y: start_y, stop_by: MoveStopBy::Natural }
     }
 
     fn is_same_tile(x: f32, y: f32, x2: f32, y2: f32) -> bool {
-        return (x / CELL_SIZE as f32).floor() as u32 == (x2 / CELL_SIZE as f32).floor() as u32 && (y / CELL_SIZE as f32).floor() as u32 == (y2 / CELL_SIZE as f32).floor() as u32
+        (x / CELL_SIZE as f32).floor() as u32 == (x2 / CELL_SIZE as f32).floor() as u32 && (y / CELL_SIZE as f32).floor() as u32 == (y2 / CELL_SIZE as f32).floor() as u32
     }
 }
 
@@ -186,4 +205,55 @@ pub enum MoveStopBy {
     Natural,
     Collision,
     Border,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Cell;
+
+    /// World of 10 by 10 cells with a 3 by 3 block of wall from (4, 4) to (6, 6).
+    fn walled_square() -> Game {
+        let mut game = Game::with_seed(10, 10, 0);
+        for y in 4..7 {
+            for x in 4..7 {
+                game.set_cell(x, y, Cell::new(Cells::Wall));
+            }
+        }
+        game
+    }
+
+    fn circle(x: f32, y: f32, radius: f32) -> Hitbox {
+        Hitbox::new(Hitboxes::Circle { x, y, radius }, Cells::Wall.hardness())
+    }
+
+    #[test]
+    fn circles_clear_of_a_wall_do_not_collide() {
+        let game = walled_square();
+        assert!(!circle(1.5, 5.5, 1.0).collides(&game));
+        assert!(!circle(2.6, 5.5, 1.3).collides(&game));
+        // the corner of the block is cut, a circle can sit where the square corner would be
+        assert!(!circle(3.7, 3.7, 0.4).collides(&game));
+    }
+
+    #[test]
+    fn circles_touching_or_inside_a_wall_collide() {
+        let game = walled_square();
+        assert!(circle(3.0, 5.5, 1.5).collides(&game));
+        assert!(circle(5.5, 8.0, 1.2).collides(&game));
+        assert!(circle(5.5, 5.5, 0.2).collides(&game));
+    }
+
+    #[test]
+    fn border_collision_decides_about_circles_leaving_the_world() {
+        let game = walled_square();
+        let mut hitbox = circle(0.5, 1.5, 1.0);
+        assert!(hitbox.collides(&game));
+        hitbox.border_collision = false;
+        assert!(!hitbox.collides(&game));
+        hitbox.kind = Hitboxes::Circle { x: 9.8, y: 9.8, radius: 0.5 };
+        assert!(!hitbox.collides(&game));
+        hitbox.border_collision = true;
+        assert!(hitbox.collides(&game));
+    }
+}
@@ -0,0 +1,267 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::autotile::{Tileset, TILE_COUNT};
+use crate::color::Color;
+use crate::game::{Cell, Cells, Game};
+use crate::render::PixelBuffer;
+
+/// Error returned when importing or exporting an image fails.
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    Decode(png::DecodingError),
+    Encode(png::EncodingError),
+    /// The pixel's colour isn't in the palette and the palette only accepts exact matches.
+    UnknownColor { x: u32, y: u32, color: Color },
+    /// A tileset image isn't a single row of `TILE_COUNT` square tiles.
+    BadTileset { width: u32, height: u32 },
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "io error: {}", err),
+            ImageError::Decode(err) => write!(f, "failed to decode png: {}", err),
+            ImageError::Encode(err) => write!(f, "failed to encode png: {}", err),
+            ImageError::UnknownColor { x, y, color } => write!(
+                f,
+                "pixel at {}, {} has colour #{:02x}{:02x}{:02x}{:02x} which isn't in the palette",
+                x, y, color.r, color.g, color.b, color.a
+            ),
+            ImageError::BadTileset { width, height } => write!(
+                f,
+                "{}x{} image isn't a row of {} square tiles",
+                width, height, TILE_COUNT
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(err) => Some(err),
+            ImageError::Decode(err) => Some(err),
+            ImageError::Encode(err) => Some(err),
+            ImageError::UnknownColor { .. } | ImageError::BadTileset { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> ImageError {
+        ImageError::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> ImageError {
+        ImageError::Decode(err)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> ImageError {
+        ImageError::Encode(err)
+    }
+}
+
+/// How pixel colours are matched against the palette.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PaletteMatch {
+    /// Only exact colours are accepted, anything else is an error.
+    Exact,
+    /// The closest colour in the palette is used.
+    Nearest,
+}
+
+/// Maps pixel colours to materials.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    entries: Vec<(Color, Cells)>,
+    pub matching: PaletteMatch,
+}
+
+impl Palette {
+    /// Creates an empty palette.
+    pub fn new(matching: PaletteMatch) -> Palette {
+        Palette { entries: Vec::new(), matching }
+    }
+
+    /// Creates a palette mapping the default colour of every material to the material.
+    pub fn from_materials(matching: PaletteMatch) -> Palette {
+        let mut palette = Palette::new(matching);
+        for kind in Cells::ALL {
+            palette = palette.with(kind.color(), kind);
+        }
+        palette
+    }
+
+    /// Adds a colour to the palette, replacing the material previously mapped to it.
+    pub fn with(mut self, color: Color, kind: Cells) -> Palette {
+        self.entries.retain(|(entry, _)| *entry != color);
+        self.entries.push((color, kind));
+        self
+    }
+
+    /// Returns the material for the colour, if the palette matches it.
+    pub fn find(&self, color: Color) -> Option<Cells> {
+        match self.matching {
+            PaletteMatch::Exact => self.entries.iter().find(|(entry, _)| *entry == color).map(|(_, kind)| *kind),
+            PaletteMatch::Nearest => self
+                .entries
+                .iter()
+                .min_by_key(|(entry, _)| Self::distance(*entry, color))
+                .map(|(_, kind)| *kind),
+        }
+    }
+
+    fn distance(a: Color, b: Color) -> u32 {
+        [(a.r, b.r), (a.g, b.g), (a.b, b.b), (a.a, b.a)]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+            .sum()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::from_materials(PaletteMatch::Nearest)
+    }
+}
+
+impl Game {
+    /// Builds a game from a png image, one cell per pixel.
+    pub fn read_png(r: impl Read, palette: &Palette) -> Result<Game, ImageError> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let channels = info.color_type.samples();
+        let mut game = Game::new(info.width, info.height);
+        for y in 0..info.height {
+            let row = &buf[y as usize * info.line_size..];
+            for x in 0..info.width {
+                let color = pixel_color(&row[x as usize * channels..(x as usize + 1) * channels]);
+                let kind = palette.find(color).ok_or(ImageError::UnknownColor { x, y, color })?;
+                game.set_cell(x as i32, y as i32, Cell::new(kind));
+            }
+        }
+        Ok(game)
+    }
+
+    /// Writes the world as a png image, every cell being a `scale` by `scale` square.
+    ///
+    /// Cells are written unlit and without the background, so `read_png` turns the image back into the same materials.
+    pub fn write_png(&self, w: impl Write, scale: u32) -> Result<(), ImageError> {
+        let mut buffer = PixelBuffer::new(scale);
+        buffer.plain_colors = true;
+        self.draw(&mut buffer, self.area());
+        buffer.write_png(w)
+    }
+
+    pub fn load_png(path: impl AsRef<Path>, palette: &Palette) -> Result<Game, ImageError> {
+        Game::read_png(BufReader::new(File::open(path)?), palette)
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>, scale: u32) -> Result<(), ImageError> {
+        self.write_png(BufWriter::new(File::create(path)?), scale)
+    }
+}
+
+/// Converts a pixel of a png normalized to 8 bit colour.
+fn pixel_color(pixel: &[u8]) -> Color {
+    match *pixel {
+        [gray] => Color::rgb(gray, gray, gray),
+        [gray, alpha] => Color::rgba(gray, gray, gray, alpha),
+        [r, g, b] => Color::rgb(r, g, b),
+        [r, g, b, a] => Color::rgba(r, g, b, a),
+        _ => unreachable!("png is normalized to 8 bit colour"),
+    }
+}
+
+impl Tileset {
+    /// Reads a row of `TILE_COUNT` square tiles, in the order of `Tile::index`.
+    pub fn read_png(r: impl Read) -> Result<Tileset, ImageError> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let channels = info.color_type.samples();
+        let bad_size = ImageError::BadTileset { width: info.width, height: info.height };
+        if info.width != info.height * TILE_COUNT {
+            return Err(bad_size);
+        }
+        let mut pixels = Vec::with_capacity((info.width * info.height) as usize);
+        for y in 0..info.height {
+            let row = &buf[y as usize * info.line_size..];
+            for x in 0..info.width as usize {
+                pixels.push(pixel_color(&row[x * channels..(x + 1) * channels]));
+            }
+        }
+        Tileset::new(info.height, pixels).ok_or(bad_size)
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Tileset, ImageError> {
+        Tileset::read_png(BufReader::new(File::open(path)?))
+    }
+}
+
+impl PixelBuffer {
+    pub fn write_png(&self, w: impl Write) -> Result<(), ImageError> {
+        let mut encoder = png::Encoder::new(w, self.width(), self.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels())?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::Lighting;
+
+    fn round_trip(game: &Game) -> Vec<Cells> {
+        let mut bytes = Vec::new();
+        game.write_png(&mut bytes, 1).unwrap();
+        let loaded = Game::read_png(bytes.as_slice(), &Palette::default()).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (game.width(), game.height()));
+        loaded.cells().iter().map(|cell| cell.kind).collect()
+    }
+
+    #[test]
+    fn export_round_trips_every_material() {
+        let mut game = Game::with_seed(Cells::ALL.len() as u32, 1, 0);
+        for kind in Cells::ALL {
+            game.set_cell(kind.index() as i32, 0, Cell::new(kind));
+        }
+        assert_eq!(round_trip(&game), Cells::ALL);
+    }
+
+    #[test]
+    fn export_ignores_lighting() {
+        let mut game = Game::with_seed(1, 3, 0);
+        game.set_cell(0, 0, Cell::new(Cells::Wall));
+        game.lighting = Some(Lighting::default());
+        assert_eq!(round_trip(&game), [Cells::Wall, Cells::Air, Cells::Air]);
+    }
+
+    #[test]
+    fn export_ignores_the_background() {
+        let mut game = Game::with_seed(2, 1, 0);
+        game.set_background(0, 0, Cells::Wall);
+        game.set_background(1, 0, Cells::Sand);
+        assert_eq!(round_trip(&game), [Cells::Air, Cells::Air]);
+    }
+}
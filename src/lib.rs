@@ -0,0 +1,36 @@
+pub mod color;
+pub mod mask;
+pub mod game;
+pub mod hitbox;
+pub mod force;
+pub mod history;
+pub mod codec;
+pub mod replay;
+pub mod save;
+pub mod image;
+pub mod text;
+pub mod stats;
+pub mod diff;
+pub mod scenario;
+pub mod resize;
+pub mod camera;
+pub mod render;
+pub mod terminal;
+pub mod overlay;
+pub mod light;
+pub mod capture;
+pub mod autotile;
+pub mod contour;
+
+pub use game::{Frame, Game, Mask};
+pub use hitbox::Hitbox;
+
+pub const WIN_WIDTH: u32 = 1600;
+pub const WIN_HEIGHT: u32 = 900;
+
+pub const CELLS_WIDTH: u32 = 100;
+pub const CELLS_HEIGHT: u32 = 100;
+
+pub const CELL_SIZE: u32 = WIN_WIDTH / CELLS_WIDTH;
+
+pub const ITERATON: u32 = CELLS_HEIGHT * CELLS_WIDTH / 10;
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+
+use crate::color::Color;
+use crate::game::{Cells, Game};
+
+/// How the world is lit when drawn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Lighting {
+    /// Light falling in from the top of the world, 0 for night.
+    pub sky: u8,
+    /// Minimum light of every cell, so unlit places aren't pitch black.
+    pub ambient: u8,
+}
+
+impl Default for Lighting {
+    fn default() -> Lighting {
+        Lighting { sky: 255, ambient: 24 }
+    }
+}
+
+/// Light level of every cell, from 0 for darkness to 255 for fully lit.
+///
+/// Sky light falls straight down through air, then light from the sky and from emissive
+/// materials spreads to neighbouring cells, losing the opacity of every cell it enters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightMap {
+    width: u32,
+    height: u32,
+    ambient: u8,
+    levels: Vec<u8>,
+}
+
+impl LightMap {
+    pub fn compute(game: &Game, lighting: Lighting) -> LightMap {
+        let (width, height) = (game.width() as i32, game.height() as i32);
+        let mut levels = vec![0u8; game.cells().len()];
+        let mut queue = VecDeque::new();
+        for x in 0..width {
+            let mut sky = lighting.sky;
+            for y in 0..height {
+                let kind = game.get_cell(x, y).kind;
+                // sunlight only falls freely through the most transparent material
+                if kind.opacity() > Cells::Air.opacity() {
+                    sky = 0;
+                }
+                let level = sky.max(kind.emission());
+                if level > 0 {
+                    levels[(y * width + x) as usize] = level;
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        while let Some((x, y)) = queue.pop_front() {
+            let level = levels[(y * width + x) as usize];
+            for (nx, ny) in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                let Some(cell) = game.try_get_cell(nx, ny) else { continue };
+                let spread = level.saturating_sub(cell.kind.opacity());
+                let neighbour = &mut levels[(ny * width + nx) as usize];
+                if spread > *neighbour {
+                    *neighbour = spread;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        LightMap {
+            width: game.width(),
+            height: game.height(),
+            ambient: lighting.ambient,
+            levels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the light level of the cell, without the ambient light.
+    pub fn get(&self, x: i32, y: i32) -> u8 {
+        self.levels[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    /// Darkens the colour by the light of the cell.
+    pub fn modulate(&self, x: i32, y: i32, color: Color) -> Color {
+        let light = self.get(x, y).max(self.ambient) as u32;
+        let scale = |channel: u8| (channel as u32 * light / 255) as u8;
+        Color::rgba(scale(color.r), scale(color.g), scale(color.b), color.a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Cell;
+
+    const NIGHT: Lighting = Lighting { sky: 0, ambient: 0 };
+
+    #[test]
+    fn sky_light_falls_through_air() {
+        let game = Game::with_seed(1, 4, 0);
+        let light = LightMap::compute(&game, Lighting::default());
+        for y in 0..4 {
+            assert_eq!(light.get(0, y), 255);
+        }
+    }
+
+    #[test]
+    fn walls_block_the_sky() {
+        // a roof over the whole world, nothing gets around it
+        let mut game = Game::with_seed(3, 4, 0);
+        for x in 0..3 {
+            game.set_cell(x, 1, Cell::new(Cells::Wall));
+        }
+        let light = LightMap::compute(&game, Lighting::default());
+        assert_eq!(light.get(1, 0), 255);
+        for x in 0..3 {
+            assert_eq!(light.get(x, 1), 0);
+            assert_eq!(light.get(x, 2), 0);
+            assert_eq!(light.get(x, 3), 0);
+        }
+    }
+
+    #[test]
+    fn light_spreads_from_an_emitter() {
+        let mut game = Game::with_seed(7, 1, 0);
+        game.set_cell(3, 0, Cell::new(Cells::Lava));
+        let light = LightMap::compute(&game, NIGHT);
+        let air = Cells::Air.opacity();
+        assert_eq!(light.get(3, 0), 255);
+        for distance in 1..=3 {
+            let expected = 255 - air * distance as u8;
+            assert_eq!(light.get(3 - distance, 0), expected);
+            assert_eq!(light.get(3 + distance, 0), expected);
+        }
+    }
+
+    #[test]
+    fn emitted_light_loses_the_opacity_of_what_it_enters() {
+        let mut game = Game::with_seed(3, 1, 0);
+        game.set_cell(0, 0, Cell::new(Cells::Lava));
+        game.set_cell(1, 0, Cell::new(Cells::Sand));
+        game.set_cell(2, 0, Cell::new(Cells::Wall));
+        let light = LightMap::compute(&game, NIGHT);
+        assert_eq!(light.get(1, 0), 255 - Cells::Sand.opacity());
+        assert_eq!(light.get(2, 0), 0);
+    }
+
+    #[test]
+    fn modulate_keeps_the_ambient_light() {
+        let light = LightMap::compute(&Game::with_seed(1, 1, 0), Lighting { sky: 0, ambient: 51 });
+        assert_eq!(light.modulate(0, 0, Color::rgb(255, 100, 0)), Color::rgb(51, 20, 0));
+    }
+}
@@ -1,27 +1,48 @@
-pub mod mask;
-pub mod game;
-pub mod hitbox;
-
-
-use game::Game;
+use jiskricka::{game, hitbox, mask, render, replay};
+use jiskricka::autotile::Tileset;
+use jiskricka::camera::Camera;
+use jiskricka::capture::Capture;
+use jiskricka::game::{Coords, Game};
+use jiskricka::light::Lighting;
+use jiskricka::overlay::Overlay;
+use jiskricka::{CELLS_HEIGHT, CELLS_WIDTH, CELL_SIZE, WIN_HEIGHT, WIN_WIDTH};
+use jiskricka::render::Renderer;
 use sfml::graphics::RenderTarget;
+use sfml::window::{mouse, Key};
 
-use crate::game::Coords;
-
-const WIN_WIDTH: u32 = 1600;
-const WIN_HEIGHT: u32 = 900;
+/// Camera panning speed in screen pixels per second.
+const PAN_SPEED: f32 = 800.0;
+/// Zoom change per mouse wheel step.
+const ZOOM_STEP: f32 = 1.25;
 
-const CELLS_WIDTH: u32 = 100;
-const CELLS_HEIGHT: u32 = 100;
-
-const CELL_SIZE: u32 = WIN_WIDTH / CELLS_WIDTH;
-
-const ITERATON: u32 = CELLS_HEIGHT * CELLS_WIDTH / 10;
+/// Returns the value following the given command line flag.
+fn arg_value(flag: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != flag).nth(1)
+}
 
 fn main() {
-    let mut game = Game::new(CELLS_WIDTH, CELLS_HEIGHT);
+    // `--world <path>` loads the world from the file if it exists and saves it there when the window closes
+    let world_path = arg_value("--world");
+    let loaded = world_path.as_ref().filter(|path| std::path::Path::new(path).exists()).map(|path| {
+        Game::load(path).unwrap_or_else(|err| panic!("failed to load world from {}: {}", path, err))
+    });
+    let is_new = loaded.is_none();
+    let mut game = loaded.unwrap_or_else(|| Game::new(CELLS_WIDTH, CELLS_HEIGHT));
     let mut frame = game::Frame::new();
 
+    // `--record <path>` saves every injected frame to a replay file when the window closes
+    let record_path = arg_value("--record");
+    let mut replay = replay::Replay::new(&game);
+
+    // `--capture <path>` writes every Nth frame (`--capture-every N`) to a gif, or to a directory of png files
+    let capture_every = arg_value("--capture-every").map_or(1, |every| every.parse().expect("--capture-every expects a number"));
+    let mut capture = arg_value("--capture").map(|path| {
+        Capture::create(&path, capture_every)
+            .unwrap_or_else(|err| panic!("failed to capture to {}: {}", path, err))
+            .with_scale(4)
+            .with_frame_time(std::time::Duration::from_secs(1) / 60)
+    });
+
     let mut rw = sfml::graphics::RenderWindow::new(
         sfml::window::VideoMode::new(WIN_WIDTH, WIN_HEIGHT, 32),
         "SFML Example",
@@ -31,16 +52,20 @@ fn main() {
     rw.set_vertical_sync_enabled(true);
     rw.set_framerate_limit(60);
 
-    let mut star = mask::FAR_MASK.clone();
-    star.coords.x += 3;
-    star.coords.y += 1;
-    for (coords, cell) in game.iter_masked_cells(&star) {
-        frame.add_action(game::Action::Cell(coords, game::CellAction::Set(game::Cell::new(game::Cells::Sand))));
-    }
-    for i in 0..20 {
-        frame.add_action(game::Action::Cell(game::Coords { x: i as i32, y: 20 }, game::CellAction::Set(game::Cell::new(game::Cells::Wall))));
+    if is_new {
+        let mut star = mask::FAR_MASK;
+        star.coords.x += 3;
+        star.coords.y += 1;
+        frame.add_mask(&star, game::CellAction::Set(game::Cell::new(game::Cells::Sand)));
+        frame.add_line(Coords { x: 0, y: 20 }, Coords { x: 19, y: 20 }, game::CellAction::Set(game::Cell::new(game::Cells::Wall)));
+        replay.apply(&mut game, &mut frame);
     }
-    game.apply_frame(&mut frame);
+
+    let mut renderer = render::TextureRenderer::new();
+    let mut contour_renderer = render::ContourRenderer::new();
+    let mut contoured = false;
+    let mut camera = Camera::new(WIN_WIDTH, WIN_HEIGHT, CELL_SIZE as f32);
+    camera.fit(game.width(), game.height());
 
     use std::time::Instant;
     let mut last_frame = Instant::now();
@@ -55,17 +80,114 @@ fn main() {
         let current_frame = Instant::now();
         let delta = current_frame.duration_since(last_frame).as_secs_f32();
         last_frame = current_frame;
-        let fps = 1.0 / delta;
+        let _fps = 1.0 / delta;
 
         while let Some(event) = rw.poll_event() {
             match event {
-                sfml::window::Event::Closed => return,
+                sfml::window::Event::Closed => {
+                    if let Some(path) = &world_path {
+                        if let Err(err) = game.save(path) {
+                            eprintln!("failed to save world to {}: {}", path, err);
+                        }
+                    }
+                    if let Some(path) = &record_path {
+                        if let Err(err) = replay.save(path) {
+                            eprintln!("failed to save replay to {}: {}", path, err);
+                        }
+                    }
+                    if let Some(capture) = capture.take() {
+                        if let Err(err) = capture.finish() {
+                            eprintln!("failed to finish capture: {}", err);
+                        }
+                    }
+                    return;
+                }
+                sfml::window::Event::Resized { width, height } => {
+                    camera.resize_screen(width, height);
+                    let (width, height) = (width as f32, height as f32);
+                    let view = sfml::graphics::View::new((width / 2.0, height / 2.0).into(), (width, height).into());
+                    rw.set_view(&view);
+                }
+                // F1 to F4 toggle the debug overlays, L the lighting, T the terrain tiles, C the smooth contours
+                sfml::window::Event::KeyPressed { code, .. } => {
+                    let keys = [Key::F1, Key::F2, Key::F3, Key::F4];
+                    if let Some(index) = keys.iter().position(|key| *key == code) {
+                        renderer.overlays.toggle(Overlay::ALL[index]);
+                    }
+                    if code == Key::T {
+                        let tileset = match renderer.tileset() {
+                            Some(_) => None,
+                            None => Some(Tileset::generated(8)),
+                        };
+                        renderer.set_tileset(tileset);
+                    }
+                    if code == Key::C {
+                        contoured = !contoured;
+                        // the texture is stale after drawing the contours instead
+                        renderer.invalidate();
+                    }
+                    if code == Key::L {
+                        game.lighting = match game.lighting {
+                            Some(_) => None,
+                            None => Some(Lighting::default()),
+                        };
+                    }
+                }
+                sfml::window::Event::MouseWheelScrolled { wheel: mouse::Wheel::VerticalWheel, delta: steps, x, y } => {
+                    camera.zoom_at(ZOOM_STEP.powf(steps), x as f32, y as f32);
+                }
                 _ => {}
             }
         }
+
+        let pan = PAN_SPEED * delta;
+        if Key::Left.is_pressed() || Key::A.is_pressed() {
+            camera.pan(-pan, 0.0);
+        }
+        if Key::Right.is_pressed() || Key::D.is_pressed() {
+            camera.pan(pan, 0.0);
+        }
+        if Key::Up.is_pressed() || Key::W.is_pressed() {
+            camera.pan(0.0, -pan);
+        }
+        if Key::Down.is_pressed() || Key::S.is_pressed() {
+            camera.pan(0.0, pan);
+        }
+
+        // left click pours sand, right click builds walls and middle click background walls into the cell under the cursor
+        let brush: Option<fn(Coords) -> game::Action> = if mouse::Button::Left.is_pressed() {
+            Some(|coords| game::Action::Cell(coords, game::CellAction::Set(game::Cell::new(game::Cells::Sand))))
+        } else if mouse::Button::Right.is_pressed() {
+            Some(|coords| game::Action::Cell(coords, game::CellAction::Set(game::Cell::new(game::Cells::Wall))))
+        } else if mouse::Button::Middle.is_pressed() {
+            Some(|coords| game::Action::Background(coords, game::BackgroundAction::Set(game::Cells::Wall)))
+        } else {
+            None
+        };
+        if let Some(brush) = brush {
+            let position = rw.mouse_position();
+            let coords = camera.screen_to_cell(position.x as f32, position.y as f32);
+            if game.cell_exists(coords.x, coords.y) {
+                frame.add_action(brush(coords));
+                replay.apply(&mut game, &mut frame);
+            }
+        }
+
         rw.clear(sfml::graphics::Color::BLACK);
         game.update(&mut frame);
-        game.draw(&mut rw);
+        if contoured {
+            contour_renderer.overlays = renderer.overlays;
+            contour_renderer.draw(&game, &mut rw, &camera);
+        } else {
+            renderer.draw(&game, &mut rw, &camera);
+        }
+        if let Some(active) = &mut capture {
+            active.overlays = renderer.overlays;
+            if let Err(err) = active.capture(&game) {
+                eprintln!("capture stopped: {}", err);
+                capture = None;
+            }
+        }
         rw.display();
     }
 }
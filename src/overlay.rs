@@ -0,0 +1,174 @@
+use crate::color::Color;
+use crate::game::{Coords, Game};
+
+/// Side of the square chunks shown by [`Overlay::Chunks`], in cells.
+pub const CHUNK_SIZE: u32 = 16;
+
+/// Debug view painted over the normal cell colours by [`Game::draw`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Overlay {
+    /// Blue for soft cells up to red for the hardest ones.
+    Hardness,
+    /// Cells changed during the last tick.
+    Updated,
+    /// Chunk boundaries, red for chunks with changes during the last tick with the changed rectangle in green.
+    Chunks,
+    /// Direction of the force fields, as a hue, with the strength as opacity.
+    Forces,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 4] = [Overlay::Hardness, Overlay::Updated, Overlay::Chunks, Overlay::Forces];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Set of enabled overlays, painted in the order of `Overlay::ALL`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Overlays {
+    bits: u8,
+}
+
+impl Overlays {
+    pub const NONE: Overlays = Overlays { bits: 0 };
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn is_enabled(&self, overlay: Overlay) -> bool {
+        self.bits & overlay.bit() != 0
+    }
+
+    pub fn set(&mut self, overlay: Overlay, enabled: bool) {
+        if enabled {
+            self.bits |= overlay.bit();
+        } else {
+            self.bits &= !overlay.bit();
+        }
+    }
+
+    /// Switches the overlay on or off, returns whether it is now enabled.
+    pub fn toggle(&mut self, overlay: Overlay) -> bool {
+        self.bits ^= overlay.bit();
+        self.is_enabled(overlay)
+    }
+
+    pub fn with(mut self, overlay: Overlay) -> Overlays {
+        self.set(overlay, true);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Overlay> + '_ {
+        Overlay::ALL.into_iter().filter(|overlay| self.is_enabled(*overlay))
+    }
+}
+
+/// Overlays prepared for one draw of a game.
+pub(crate) struct OverlayPass {
+    overlays: Overlays,
+    /// Tick stamped on cells changed during the last tick.
+    last_tick: Option<u64>,
+    chunks_width: u32,
+    /// Rectangle of cells changed during the last tick of every chunk.
+    dirty: Vec<Option<(Coords, Coords)>>,
+}
+
+impl OverlayPass {
+    pub(crate) fn new(game: &Game, overlays: Overlays) -> OverlayPass {
+        let last_tick = game.tick().checked_sub(1);
+        let chunks_width = game.width().div_ceil(CHUNK_SIZE);
+        let mut dirty: Vec<Option<(Coords, Coords)>> = Vec::new();
+        if overlays.is_enabled(Overlay::Chunks) {
+            dirty = vec![None; (chunks_width * game.height().div_ceil(CHUNK_SIZE)) as usize];
+            for y in 0..game.height() as i32 {
+                for x in 0..game.width() as i32 {
+                    if last_tick.is_none() || game.changed_at(x, y) != last_tick {
+                        continue;
+                    }
+                    let chunk = &mut dirty[Self::chunk_index(chunks_width, x, y)];
+                    let coords = Coords { x, y };
+                    *chunk = Some(match *chunk {
+                        Some((min, max)) => (
+                            Coords { x: min.x.min(x), y: min.y.min(y) },
+                            Coords { x: max.x.max(x), y: max.y.max(y) },
+                        ),
+                        None => (coords, coords),
+                    });
+                }
+            }
+        }
+        OverlayPass { overlays, last_tick, chunks_width, dirty }
+    }
+
+    fn chunk_index(chunks_width: u32, x: i32, y: i32) -> usize {
+        (y as u32 / CHUNK_SIZE * chunks_width + x as u32 / CHUNK_SIZE) as usize
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.overlays.is_empty()
+    }
+
+    /// Paints the enabled overlays over the colour of the cell.
+    pub(crate) fn apply(&self, game: &Game, x: i32, y: i32, mut color: Color) -> Color {
+        for overlay in self.overlays.iter() {
+            if let Some(over) = self.paint(game, overlay, x, y) {
+                color = color.blend(over);
+            }
+        }
+        color
+    }
+
+    fn paint(&self, game: &Game, overlay: Overlay, x: i32, y: i32) -> Option<Color> {
+        match overlay {
+            Overlay::Hardness => {
+                let heat = (game.get_cell(x, y).hardness.clamp(0, 100) * 255 / 100) as u8;
+                Some(Color::rgba(heat, 0, 255 - heat, 160))
+            }
+            Overlay::Updated => {
+                (self.last_tick.is_some() && game.changed_at(x, y) == self.last_tick).then_some(Color::rgba(255, 0, 255, 200))
+            }
+            Overlay::Chunks => {
+                let dirty = self.dirty[Self::chunk_index(self.chunks_width, x, y)];
+                let on_border = (x as u32).is_multiple_of(CHUNK_SIZE) || (y as u32).is_multiple_of(CHUNK_SIZE);
+                match dirty {
+                    Some((min, max)) if on_rect_border(x, y, min, max) => Some(Color::rgba(0, 255, 0, 220)),
+                    Some(_) if on_border => Some(Color::rgba(255, 0, 0, 200)),
+                    None if on_border => Some(Color::rgba(128, 128, 128, 120)),
+                    _ => None,
+                }
+            }
+            Overlay::Forces => {
+                let coords = Coords { x, y };
+                let (mut dx, mut dy, mut strength) = (0.0, 0.0, 0.0f32);
+                for field in game.force_fields.iter().filter(|field| field.region.contains(coords)) {
+                    dx += field.direction.0 * field.strength;
+                    dy += field.direction.1 * field.strength;
+                    strength += field.strength;
+                }
+                if strength <= 0.0 || (dx == 0.0 && dy == 0.0) {
+                    return None;
+                }
+                let hue = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+                Some(Color { a: (strength.min(1.0) * 200.0) as u8, ..hue_color(hue) })
+            }
+        }
+    }
+}
+
+fn on_rect_border(x: i32, y: i32, min: Coords, max: Coords) -> bool {
+    let inside = x >= min.x && x <= max.x && y >= min.y && y <= max.y;
+    inside && (x == min.x || x == max.x || y == min.y || y == max.y)
+}
+
+/// Returns the fully saturated colour of the hue, in degrees.
+fn hue_color(hue: f32) -> Color {
+    let channel = |offset: f32| {
+        let k = (offset + hue / 60.0) % 6.0;
+        let value = 1.0 - k.min(4.0 - k).clamp(0.0, 1.0);
+        (value * 255.0) as u8
+    };
+    Color::rgb(channel(5.0), channel(3.0), channel(1.0))
+}
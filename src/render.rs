@@ -0,0 +1,468 @@
+#[cfg(feature = "sfml")]
+use sfml::graphics::{Image, IntRect, PrimitiveType, RenderStates, RenderTarget, RenderTexture, Sprite, Texture, Transformable, Vertex};
+#[cfg(feature = "sfml")]
+use sfml::SfBox;
+
+use crate::autotile::{Tile, Tileset};
+use crate::camera::Area;
+#[cfg(feature = "sfml")]
+use crate::camera::Camera;
+use crate::color::Color;
+#[cfg(feature = "sfml")]
+use crate::contour::{Contours, Point};
+use crate::game::{Cells, Game};
+#[cfg(feature = "sfml")]
+use crate::game::Coords;
+#[cfg(feature = "sfml")]
+use crate::mask::FAR_MASK;
+use crate::light::LightMap;
+use crate::overlay::{OverlayPass, Overlays};
+
+/// Output of [`Game::draw`], receiving the final colour of every drawn cell.
+pub trait Renderer {
+    /// Called before any cell of the area is drawn.
+    fn begin(&mut self, _game: &Game, _area: Area) {}
+
+    /// Returns whether the cell has to be drawn, renderers keeping their output between draws can skip unchanged cells.
+    fn needs_redraw(&self, _game: &Game, _x: i32, _y: i32) -> bool {
+        true
+    }
+
+    fn draw_cell(&mut self, x: i32, y: i32, color: Color);
+
+    /// Returns the tileset for autotiled materials, without one they are drawn as plain cells.
+    fn tileset(&self) -> Option<&Tileset> {
+        None
+    }
+
+    /// Draws a cell of an autotiled material, called instead of `draw_cell` when the renderer has a tileset.
+    fn draw_tile(&mut self, x: i32, y: i32, color: Color, _tile: Tile) {
+        self.draw_cell(x, y, color);
+    }
+
+    /// Returns the debug overlays to paint over the cells.
+    fn overlays(&self) -> Overlays {
+        Overlays::NONE
+    }
+
+    /// Returns whether cells are drawn unlit in their own colour, without the background showing through air,
+    /// so the output maps back to the materials.
+    fn plain_colors(&self) -> bool {
+        false
+    }
+
+    /// Called after every cell of the area was drawn.
+    fn finish(&mut self, _game: &Game, _area: Area) {}
+}
+
+impl Game {
+    /// Walks the cells of the area, clipped to the world, and passes their colours to the renderer.
+    pub fn draw(&self, renderer: &mut impl Renderer, area: Area) {
+        let area = area.clip(self.width(), self.height());
+        let plain = renderer.plain_colors();
+        let light = self.lighting.filter(|_| !plain).map(|lighting| LightMap::compute(self, lighting));
+        let overlay = OverlayPass::new(self, renderer.overlays());
+        let tiled = renderer.tileset().is_some();
+        renderer.begin(self, area);
+        for y in area.y..area.y + area.height as i32 {
+            for x in area.x..area.x + area.width as i32 {
+                if renderer.needs_redraw(self, x, y) {
+                    let mut color = if plain { self.get_cell(x, y).color() } else { self.cell_color(x, y) };
+                    if let Some(light) = &light {
+                        color = light.modulate(x, y, color);
+                    }
+                    if !overlay.is_empty() {
+                        color = overlay.apply(self, x, y, color);
+                    }
+                    if tiled && self.get_cell(x, y).kind.is_autotiled() {
+                        renderer.draw_tile(x, y, color, Tile::of(self, x, y));
+                    } else {
+                        renderer.draw_cell(x, y, color);
+                    }
+                }
+            }
+        }
+        renderer.finish(self, area);
+    }
+
+    /// Returns the colour of the cell, showing the background through air.
+    pub fn cell_color(&self, x: i32, y: i32) -> Color {
+        let cell = self.get_cell(x, y);
+        match self.get_background(x, y) {
+            background if cell.kind == Cells::Air && background != Cells::Air => background.background_color(),
+            _ => cell.color(),
+        }
+    }
+
+    /// Returns the area covering the whole world.
+    pub fn area(&self) -> Area {
+        Area { x: 0, y: 0, width: self.width(), height: self.height() }
+    }
+}
+
+/// Paints a cell as a `scale` by `scale` square into RGBA pixels `width` pixels wide, with the tile over it if given.
+fn paint_cell(pixels: &mut [u8], width: usize, left: usize, top: usize, scale: u32, color: Color, tile: Option<(&Tileset, Tile)>) {
+    for y in 0..scale {
+        for x in 0..scale {
+            let painted = match tile {
+                Some((tileset, tile)) => {
+                    color.blend(tileset.pixel(tile, x * tileset.size() / scale, y * tileset.size() / scale))
+                }
+                None => color,
+            };
+            let index = ((top + y as usize) * width + left + x as usize) * 4;
+            pixels[index..index + 4].copy_from_slice(&[painted.r, painted.g, painted.b, painted.a]);
+        }
+    }
+}
+
+/// In-memory RGBA image of the drawn area, every cell being a `scale` by `scale` square.
+///
+/// Works without a display, for thumbnails, exports and tests.
+#[derive(Debug, Clone, Default)]
+pub struct PixelBuffer {
+    pub overlays: Overlays,
+    pub tileset: Option<Tileset>,
+    /// Draws cells unlit and without the background, for exports read back as a world.
+    pub plain_colors: bool,
+    scale: u32,
+    area: Area,
+    /// RGBA pixels, row by row.
+    pixels: Vec<u8>,
+}
+
+impl PixelBuffer {
+    pub fn new(scale: u32) -> PixelBuffer {
+        PixelBuffer {
+            overlays: Overlays::NONE,
+            tileset: None,
+            plain_colors: false,
+            scale: scale.max(1),
+            area: Area::default(),
+            pixels: Vec::new(),
+        }
+    }
+
+    /// Draws the whole world into a new buffer.
+    pub fn of(game: &Game, scale: u32) -> PixelBuffer {
+        let mut buffer = PixelBuffer::new(scale);
+        game.draw(&mut buffer, game.area());
+        buffer
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u32 {
+        self.area.width * self.scale
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u32 {
+        self.area.height * self.scale
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Returns the colour of the pixel, `None` if it is outside of the buffer.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+        let index = ((y * self.width() + x) * 4) as usize;
+        let pixel = &self.pixels[index..index + 4];
+        Some(Color::rgba(pixel[0], pixel[1], pixel[2], pixel[3]))
+    }
+}
+
+impl Renderer for PixelBuffer {
+    fn begin(&mut self, _game: &Game, area: Area) {
+        self.area = area;
+        self.pixels.clear();
+        self.pixels.resize((self.width() * self.height() * 4) as usize, 0);
+    }
+
+    fn draw_cell(&mut self, x: i32, y: i32, color: Color) {
+        let width = self.width() as usize;
+        let left = (x - self.area.x) as usize * self.scale as usize;
+        let top = (y - self.area.y) as usize * self.scale as usize;
+        paint_cell(&mut self.pixels, width, left, top, self.scale, color, None);
+    }
+
+    fn tileset(&self) -> Option<&Tileset> {
+        self.tileset.as_ref()
+    }
+
+    fn draw_tile(&mut self, x: i32, y: i32, color: Color, tile: Tile) {
+        let width = self.width() as usize;
+        let left = (x - self.area.x) as usize * self.scale as usize;
+        let top = (y - self.area.y) as usize * self.scale as usize;
+        let tile = self.tileset.as_ref().map(|tileset| (tileset, tile));
+        paint_cell(&mut self.pixels, width, left, top, self.scale, color, tile);
+    }
+
+    fn overlays(&self) -> Overlays {
+        self.overlays
+    }
+
+    fn plain_colors(&self) -> bool {
+        self.plain_colors
+    }
+}
+
+/// Draws the cell grid as a single texture, one pixel per cell, scaled up when drawn.
+/// With a tileset every cell gets as many pixels as a tile has.
+///
+/// Only cells changed since the last draw are repainted into the pixel buffer,
+/// the buffer is then uploaded once per frame.
+#[cfg(feature = "sfml")]
+pub struct TextureRenderer {
+    pub overlays: Overlays,
+    texture: SfBox<Texture>,
+    tileset: Option<Tileset>,
+    /// RGBA pixels, row by row.
+    pixels: Vec<u8>,
+    /// Size of the world in cells.
+    size: (u32, u32),
+    /// Size of a cell in texture pixels.
+    cell_pixels: u32,
+    /// Tick of the game at the last draw, `None` if everything has to be repainted.
+    drawn_tick: Option<u64>,
+    /// Area walked at the last draw, cells outside of it may be stale.
+    drawn_area: Area,
+}
+
+#[cfg(feature = "sfml")]
+impl TextureRenderer {
+    pub fn new() -> TextureRenderer {
+        TextureRenderer {
+            overlays: Overlays::NONE,
+            texture: Texture::new().expect("failed to create texture"),
+            tileset: None,
+            pixels: Vec::new(),
+            size: (0, 0),
+            cell_pixels: 1,
+            drawn_tick: None,
+            drawn_area: Area::default(),
+        }
+    }
+
+    /// Forces every cell to be repainted on the next draw, needed when drawing a different game.
+    pub fn invalidate(&mut self) {
+        self.drawn_tick = None;
+    }
+
+    /// Replaces the tileset of autotiled materials, `None` draws them as plain cells.
+    pub fn set_tileset(&mut self, tileset: Option<Tileset>) {
+        self.tileset = tileset;
+        // the texture is recreated with the new cell size on the next draw
+        self.size = (0, 0);
+    }
+
+    /// Repaints the changed cells of the area and uploads the pixels to the texture.
+    pub fn update(&mut self, game: &Game, area: Area) {
+        game.draw(self, area);
+    }
+
+    /// Updates the texture and draws the part of it visible through the camera.
+    pub fn draw(&mut self, game: &Game, target: &mut impl RenderTarget, camera: &Camera) {
+        let area = camera.visible_area(game.width(), game.height());
+        self.update(game, area);
+        if area.is_empty() {
+            return;
+        }
+        let cell = self.cell_pixels as i32;
+        let rect = IntRect::new(area.x * cell, area.y * cell, area.width as i32 * cell, area.height as i32 * cell);
+        let mut sprite = Sprite::with_texture_and_rect(&self.texture, rect);
+        sprite.set_position(camera.world_to_screen(area.x as f32, area.y as f32));
+        let scale = camera.zoom / self.cell_pixels as f32;
+        sprite.set_scale((scale, scale));
+        target.draw(&sprite);
+    }
+
+    /// Draws the view of the camera into an offscreen texture and returns it as an image.
+    pub fn render_to_image(&mut self, game: &Game, camera: &Camera) -> Option<Image> {
+        let mut target = RenderTexture::new(camera.screen_width, camera.screen_height)?;
+        target.clear(sfml::graphics::Color::TRANSPARENT);
+        self.draw(game, &mut target, camera);
+        target.display();
+        target.texture().copy_to_image()
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl Renderer for TextureRenderer {
+    fn begin(&mut self, game: &Game, area: Area) {
+        let size = (game.width(), game.height());
+        if size != self.size {
+            self.cell_pixels = self.tileset.as_ref().map_or(1, Tileset::size);
+            let (width, height) = (size.0 * self.cell_pixels, size.1 * self.cell_pixels);
+            assert!(self.texture.create(width, height), "failed to create {}x{} texture", width, height);
+            self.pixels = vec![0; (width * height * 4) as usize];
+            self.size = size;
+            self.drawn_tick = None;
+        }
+        if area != self.drawn_area {
+            self.drawn_tick = None;
+            self.drawn_area = area;
+        }
+    }
+
+    fn needs_redraw(&self, game: &Game, x: i32, y: i32) -> bool {
+        let Some(drawn) = self.drawn_tick else { return true };
+        let changed = |x: i32, y: i32| game.changed_at(x, y).is_some_and(|tick| tick >= drawn);
+        if self.tileset.is_none() {
+            return changed(x, y);
+        }
+        // tiles depend on the neighbours
+        let mut mask = FAR_MASK;
+        mask.coords = Coords { x, y };
+        changed(x, y) || mask.iter_coords().any(|coords| game.cell_exists(coords.x, coords.y) && changed(coords.x, coords.y))
+    }
+
+    fn draw_cell(&mut self, x: i32, y: i32, color: Color) {
+        let (width, cell) = ((self.size.0 * self.cell_pixels) as usize, self.cell_pixels);
+        paint_cell(&mut self.pixels, width, x as usize * cell as usize, y as usize * cell as usize, cell, color, None);
+    }
+
+    fn tileset(&self) -> Option<&Tileset> {
+        self.tileset.as_ref()
+    }
+
+    fn draw_tile(&mut self, x: i32, y: i32, color: Color, tile: Tile) {
+        let (width, cell) = ((self.size.0 * self.cell_pixels) as usize, self.cell_pixels);
+        let tile = self.tileset.as_ref().map(|tileset| (tileset, tile));
+        paint_cell(&mut self.pixels, width, x as usize * cell as usize, y as usize * cell as usize, cell, color, tile);
+    }
+
+    fn overlays(&self) -> Overlays {
+        self.overlays
+    }
+
+    fn finish(&mut self, game: &Game, _area: Area) {
+        // SAFETY: the texture was created with the same size as the pixel buffer
+        unsafe {
+            let cell = self.cell_pixels;
+            self.texture.update_from_pixels(&self.pixels, self.size.0 * cell, self.size.1 * cell, 0, 0);
+        }
+        // light and overlays can change without the cell changing, so they are repainted every time
+        let changing = game.lighting.is_some() || !self.overlays.is_empty();
+        self.drawn_tick = (!changing).then_some(game.tick());
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl Default for TextureRenderer {
+    fn default() -> TextureRenderer {
+        TextureRenderer::new()
+    }
+}
+
+/// Draws solid and liquid materials as smooth shapes traced with marching squares, outlined in a darker shade,
+/// and every other cell as a plain square.
+///
+/// The shapes are rebuilt on every draw and drawn without light or overlays, meant for high zoom levels
+/// and as a stylized look.
+#[cfg(feature = "sfml")]
+pub struct ContourRenderer {
+    pub overlays: Overlays,
+    area: Area,
+    /// Colour of the background behind each contoured cell of the area, `None` for the other cells.
+    under: Vec<Option<Color>>,
+    /// Triangles of the cells and the shapes over them, in world coordinates.
+    triangles: Vec<Vertex>,
+    /// Outline segments, in world coordinates.
+    lines: Vec<Vertex>,
+}
+
+#[cfg(feature = "sfml")]
+impl ContourRenderer {
+    pub fn new() -> ContourRenderer {
+        ContourRenderer {
+            overlays: Overlays::NONE,
+            area: Area::default(),
+            under: Vec::new(),
+            triangles: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the cells and shapes visible through the camera and draws them.
+    pub fn draw(&mut self, game: &Game, target: &mut impl RenderTarget, camera: &Camera) {
+        game.draw(self, camera.visible_area(game.width(), game.height()));
+        let to_screen = |vertex: &Vertex| {
+            Vertex::with_pos_color(camera.world_to_screen(vertex.position.x, vertex.position.y).into(), vertex.color)
+        };
+        let triangles: Vec<Vertex> = self.triangles.iter().map(to_screen).collect();
+        let lines: Vec<Vertex> = self.lines.iter().map(to_screen).collect();
+        target.draw_primitives(&triangles, PrimitiveType::TRIANGLES, &RenderStates::DEFAULT);
+        target.draw_primitives(&lines, PrimitiveType::LINES, &RenderStates::DEFAULT);
+    }
+
+    fn push_polygon(&mut self, polygon: &[Point], color: Color) {
+        for i in 1..polygon.len().saturating_sub(1) {
+            for point in [polygon[0], polygon[i], polygon[i + 1]] {
+                self.triangles.push(Vertex::with_pos_color(point.into(), color.into()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl Renderer for ContourRenderer {
+    fn begin(&mut self, game: &Game, area: Area) {
+        self.area = area;
+        self.triangles.clear();
+        self.lines.clear();
+        self.under.clear();
+        for y in area.y..area.y + area.height as i32 {
+            for x in area.x..area.x + area.width as i32 {
+                let under = match game.get_background(x, y) {
+                    Cells::Air => Cells::Air.color(),
+                    background => background.background_color(),
+                };
+                self.under.push(game.get_cell(x, y).kind.is_contoured().then_some(under));
+            }
+        }
+    }
+
+    fn draw_cell(&mut self, x: i32, y: i32, color: Color) {
+        let index = ((y - self.area.y) * self.area.width as i32 + x - self.area.x) as usize;
+        let color = self.under[index].unwrap_or(color);
+        let (x, y) = (x as f32, y as f32);
+        self.push_polygon(&[(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)], color);
+    }
+
+    fn overlays(&self) -> Overlays {
+        self.overlays
+    }
+
+    fn finish(&mut self, game: &Game, area: Area) {
+        for kind in Cells::ALL.into_iter().filter(Cells::is_contoured) {
+            let contours = Contours::extract(game, area, |x, y| game.get_cell(x, y).kind == kind);
+            for polygon in &contours.fills {
+                self.push_polygon(polygon, kind.color());
+            }
+            let outline = kind.color().blend(Color::rgba(128, 128, 128, 128)).into();
+            for (a, b) in contours.segments {
+                self.lines.push(Vertex::with_pos_color(a.into(), outline));
+                self.lines.push(Vertex::with_pos_color(b.into(), outline));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl Default for ContourRenderer {
+    fn default() -> ContourRenderer {
+        ContourRenderer::new()
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::codec::{self, DecodeError};
+use crate::game::{Action, Cell, Cells, ConflictPolicy, Frame, Game};
+
+const MAGIC: &[u8; 4] = b"JSKP";
+const VERSION: u16 = 2;
+/// First version with the background layer.
+const BACKGROUND_VERSION: u16 = 2;
+
+/// Recording of every frame injected into a game from the outside.
+///
+/// Together with the starting world and the seed, this is enough to rebuild the exact simulation.
+/// Force fields aren't recorded, set them up on the player's game before stepping.
+pub struct Replay {
+    pub width: u32,
+    pub height: u32,
+    pub iteration: u32,
+    pub seed: u64,
+    /// Tick of the game when the recording started.
+    pub start_tick: u64,
+    /// Cells of the game when the recording started, row by row.
+    pub cells: Vec<Cell>,
+    /// Background of the game when the recording started, row by row.
+    pub background: Vec<Cells>,
+    pub inputs: Vec<ReplayInput>,
+}
+
+/// Frame injected into the game before the update of the given tick.
+pub struct ReplayInput {
+    pub tick: u64,
+    pub policy: ConflictPolicy,
+    /// Actions in the order they were polled from the frame.
+    pub actions: Vec<Action>,
+}
+
+impl Replay {
+    /// Starts recording the game from its current state.
+    ///
+    /// Recording has to start at a tick boundary, panics if a frame with random conflicts
+    /// was already applied during the current tick, the replay couldn't pick the same winners.
+    pub fn new(game: &Game) -> Replay {
+        assert!(!game.is_mid_tick(), "replays have to start before any random conflict of the tick is resolved");
+        Replay {
+            width: game.width(),
+            height: game.height(),
+            iteration: game.iteration,
+            seed: game.seed(),
+            start_tick: game.tick(),
+            cells: game.cells().to_vec(),
+            background: game.background().to_vec(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Records the frame as injected at the game's current tick, without consuming it.
+    pub fn record(&mut self, game: &Game, frame: &Frame) {
+        let mut frame = frame.clone();
+        let mut actions = Vec::with_capacity(frame.len());
+        while let Some(action) = frame.poll() {
+            actions.push(action);
+        }
+        self.inputs.push(ReplayInput {
+            tick: game.tick(),
+            policy: frame.policy(),
+            actions,
+        });
+    }
+
+    /// Records the frame and applies it to the game.
+    pub fn apply(&mut self, game: &mut Game, frame: &mut Frame) -> usize {
+        self.record(game, frame);
+        game.apply_frame(frame)
+    }
+
+    /// Returns a player rebuilding the simulation from the start of the recording.
+    pub fn player(&self) -> ReplayPlayer<'_> {
+        let mut game = Game::from_parts(self.width, self.height, self.cells.clone(), self.seed, self.start_tick);
+        game.iteration = self.iteration;
+        game.set_background_layer(self.background.clone());
+        ReplayPlayer {
+            replay: self,
+            game,
+            next_input: 0,
+        }
+    }
+
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        codec::write_header(&mut w, MAGIC, VERSION)?;
+        codec::write_u32(&mut w, self.width)?;
+        codec::write_u32(&mut w, self.height)?;
+        codec::write_u32(&mut w, self.iteration)?;
+        codec::write_u64(&mut w, self.seed)?;
+        codec::write_u64(&mut w, self.start_tick)?;
+        for cell in &self.cells {
+            codec::write_cell(&mut w, cell)?;
+        }
+        codec::write_kind_runs(&mut w, &self.background)?;
+        codec::write_u32(&mut w, self.inputs.len() as u32)?;
+        for input in &self.inputs {
+            codec::write_u64(&mut w, input.tick)?;
+            codec::write_policy(&mut w, input.policy)?;
+            codec::write_u32(&mut w, input.actions.len() as u32)?;
+            for action in &input.actions {
+                codec::write_action(&mut w, action)?;
+            }
+        }
+        w.flush()
+    }
+
+    pub fn read(mut r: impl Read) -> Result<Replay, DecodeError> {
+        let version = codec::read_header(&mut r, MAGIC)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let width = codec::read_u32(&mut r)?;
+        let height = codec::read_u32(&mut r)?;
+        let iteration = codec::read_u32(&mut r)?;
+        let seed = codec::read_u64(&mut r)?;
+        let start_tick = codec::read_u64(&mut r)?;
+        let count = width
+            .checked_mul(height)
+            .ok_or(DecodeError::Corrupt("world dimensions overflow"))?;
+        let mut cells = Vec::new();
+        for _ in 0..count {
+            cells.push(codec::read_cell(&mut r)?);
+        }
+        let background = if version >= BACKGROUND_VERSION {
+            codec::read_kind_runs(&mut r, count as usize)?
+        } else {
+            vec![Cells::Air; count as usize]
+        };
+        let input_count = codec::read_u32(&mut r)?;
+        let mut inputs = Vec::new();
+        for _ in 0..input_count {
+            let tick = codec::read_u64(&mut r)?;
+            let policy = codec::read_policy(&mut r)?;
+            let action_count = codec::read_u32(&mut r)?;
+            let mut actions = Vec::new();
+            for _ in 0..action_count {
+                actions.push(codec::read_action(&mut r)?);
+            }
+            inputs.push(ReplayInput { tick, policy, actions });
+        }
+        Ok(Replay { width, height, iteration, seed, start_tick, cells, background, inputs })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Replay, DecodeError> {
+        Replay::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Plays a replay back, tick by tick.
+pub struct ReplayPlayer<'a> {
+    replay: &'a Replay,
+    game: Game,
+    next_input: usize,
+}
+
+impl<'a> ReplayPlayer<'a> {
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    /// Returns true once every recorded input has been applied.
+    pub fn is_finished(&self) -> bool {
+        self.next_input >= self.replay.inputs.len()
+    }
+
+    /// Applies the inputs recorded for the current tick and updates the game.
+    pub fn step(&mut self) {
+        while let Some(input) = self.replay.inputs.get(self.next_input) {
+            if input.tick > self.game.tick() {
+                break;
+            }
+            let mut frame = Frame::with_policy(input.policy);
+            for action in &input.actions {
+                frame.add_action(*action);
+            }
+            self.game.apply_frame(&mut frame);
+            self.next_input += 1;
+        }
+        self.game.update(&mut Frame::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{CellAction, Coords};
+
+    /// Pours sand from both sides into the same cells, so the random policy has conflicts to resolve.
+    fn contested_frame(tick: u64) -> Frame {
+        let mut frame = Frame::with_policy(ConflictPolicy::Random);
+        let x = (tick % 6) as i32 + 1;
+        for (from, kind) in [(x - 1, Cells::Sand), (x + 1, Cells::Wall)] {
+            frame.add_action(Action::Cell(Coords { x: from, y: 0 }, CellAction::SetKind(kind)));
+            frame.add_action(Action::Swap(Coords { x: from, y: 0 }, Coords { x, y: 1 }));
+        }
+        frame
+    }
+
+    #[test]
+    fn playback_rebuilds_the_recorded_simulation() {
+        let mut game = Game::with_seed(8, 6, 1234);
+        let mut replay = Replay::new(&game);
+        let mut dropped = 0;
+        for tick in 0..30 {
+            if tick % 3 == 0 {
+                dropped += replay.apply(&mut game, &mut contested_frame(tick));
+            }
+            game.update(&mut Frame::new());
+        }
+        assert!(dropped > 0, "the frames never conflicted");
+
+        let mut bytes = Vec::new();
+        replay.write(&mut bytes).unwrap();
+        let loaded = Replay::read(bytes.as_slice()).unwrap();
+        let mut player = loaded.player();
+        while player.game().tick() < game.tick() {
+            player.step();
+        }
+        assert!(player.is_finished());
+        assert_eq!(player.game().cells(), game.cells());
+        assert_eq!(player.game().background(), game.background());
+    }
+
+    #[test]
+    #[should_panic(expected = "replays have to start")]
+    fn recording_after_a_random_conflict_in_the_tick_panics() {
+        let mut game = Game::with_seed(8, 6, 0);
+        game.apply_frame(&mut contested_frame(0));
+        Replay::new(&game);
+    }
+
+    #[test]
+    fn recording_can_start_on_the_next_tick() {
+        let mut game = Game::with_seed(8, 6, 0);
+        game.apply_frame(&mut contested_frame(0));
+        game.update(&mut Frame::new());
+        assert_eq!(Replay::new(&game).start_tick, 1);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::game::{Cell, Cells, Game};
+
+/// Point of the world that stays in place when resizing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Returns where the old world's top left corner ends up in the resized world.
+    fn offset(&self, old: (u32, u32), new: (u32, u32)) -> (i32, i32) {
+        let dx = new.0 as i32 - old.0 as i32;
+        let dy = new.1 as i32 - old.1 as i32;
+        let x = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => dx / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => dx,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+            Anchor::Left | Anchor::Center | Anchor::Right => dy / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => dy,
+        };
+        (x, y)
+    }
+}
+
+impl Game {
+    /// Changes the size of the world, new space is filled with the material.
+    ///
+    /// A width or height of 0 leaves a world without cells, which still updates but never changes.
+    pub fn resize(&mut self, width: u32, height: u32, anchor: Anchor, fill: Cells) {
+        let (x, y) = anchor.offset((self.width(), self.height()), (width, height));
+        self.remap(width, height, x, y, fill);
+    }
+
+    /// Keeps only the given rectangle of the world, parts of it outside the world are filled with the material.
+    pub fn crop(&mut self, x: i32, y: i32, width: u32, height: u32, fill: Cells) {
+        self.remap(width, height, -x, -y, fill);
+    }
+
+    /// Moves everything in the world by the offset, uncovered cells are filled with the material.
+    pub fn shift(&mut self, dx: i32, dy: i32, fill: Cells) {
+        self.remap(self.width(), self.height(), dx, dy, fill);
+    }
+
+    /// Rebuilds the world with the old cell at `(x, y)` moved to `(x + dx, y + dy)`.
+    fn remap(&mut self, width: u32, height: u32, dx: i32, dy: i32, fill: Cells) {
+        let fill = Cell::new(fill);
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        let mut background = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                cells.push(self.try_get_cell(x - dx, y - dy).copied().unwrap_or(fill));
+                background.push(self.try_get_background(x - dx, y - dy).unwrap_or(Cells::Air));
+            }
+        }
+        self.replace_cells(width, height, cells, background);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Frame;
+
+    #[test]
+    fn empty_worlds_still_update() {
+        let mut resized = Game::with_seed(4, 4, 0);
+        resized.resize(0, 3, Anchor::Center, Cells::Air);
+        let mut cropped = Game::with_seed(4, 4, 0);
+        cropped.crop(1, 1, 2, 0, Cells::Air);
+        for game in [&mut resized, &mut cropped, &mut Game::with_seed(0, 0, 0)] {
+            assert!(game.cells().is_empty());
+            game.update(&mut Frame::new());
+            assert_eq!(game.tick(), 1);
+        }
+    }
+
+    #[test]
+    fn resize_keeps_the_anchored_cells() {
+        let mut game = Game::with_seed(3, 3, 0);
+        game.set_cell(2, 2, Cell::new(Cells::Sand));
+        game.set_background(2, 2, Cells::Wall);
+        game.resize(5, 4, Anchor::BottomRight, Cells::Wall);
+        assert_eq!((game.width(), game.height()), (5, 4));
+        assert_eq!(game.get_cell(4, 3).kind, Cells::Sand);
+        assert_eq!(game.get_background(4, 3), Cells::Wall);
+        assert_eq!(game.get_cell(0, 0).kind, Cells::Wall);
+        assert_eq!(game.get_background(0, 0), Cells::Air);
+    }
+}
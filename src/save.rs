@@ -0,0 +1,187 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::codec::{self, DecodeError};
+use crate::game::Game;
+
+const MAGIC: &[u8; 4] = b"JSKS";
+/// Bump when the layout changes.
+const VERSION: u16 = 2;
+/// First version with the background layer.
+const BACKGROUND_VERSION: u16 = 2;
+
+impl Game {
+    /// Writes the world, the tick count and the random state.
+    ///
+    /// Cells and the background are run-length encoded, so large areas of the same material take only a few bytes.
+    pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
+        codec::write_header(&mut w, MAGIC, VERSION)?;
+        codec::write_u32(&mut w, self.width())?;
+        codec::write_u32(&mut w, self.height())?;
+        codec::write_u32(&mut w, self.iteration)?;
+        codec::write_u64(&mut w, self.seed())?;
+        codec::write_u64(&mut w, self.tick())?;
+        let mut cells = self.cells().iter().peekable();
+        while let Some(cell) = cells.next() {
+            let mut run = 1u32;
+            while cells.next_if_eq(&cell).is_some() {
+                run += 1;
+            }
+            codec::write_u32(&mut w, run)?;
+            codec::write_cell(&mut w, cell)?;
+        }
+        codec::write_kind_runs(&mut w, self.background())?;
+        w.flush()
+    }
+
+    pub fn read_from(mut r: impl Read) -> Result<Game, DecodeError> {
+        let version = codec::read_header(&mut r, MAGIC)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let width = codec::read_u32(&mut r)?;
+        let height = codec::read_u32(&mut r)?;
+        let iteration = codec::read_u32(&mut r)?;
+        let seed = codec::read_u64(&mut r)?;
+        let tick = codec::read_u64(&mut r)?;
+        let count = width
+            .checked_mul(height)
+            .ok_or(DecodeError::Corrupt("world dimensions overflow"))? as usize;
+        let mut cells = Vec::new();
+        while cells.len() < count {
+            let run = codec::read_u32(&mut r)? as usize;
+            if run == 0 || cells.len() + run > count {
+                return Err(DecodeError::Corrupt("cell run doesn't fit the world"));
+            }
+            let cell = codec::read_cell(&mut r)?;
+            cells.resize(cells.len() + run, cell);
+        }
+        let mut game = Game::from_parts(width, height, cells, seed, tick);
+        game.iteration = iteration;
+        if version >= BACKGROUND_VERSION {
+            game.set_background_layer(codec::read_kind_runs(&mut r, count)?);
+        }
+        Ok(game)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Game, DecodeError> {
+        Game::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Cell, Cells};
+
+    fn sample_game() -> Game {
+        let mut game = Game::with_seed(6, 4, 42);
+        game.iteration = 7;
+        for x in 0..6 {
+            game.set_cell(x, 3, Cell::new(Cells::Wall));
+        }
+        game.set_cell(2, 0, Cell::new(Cells::Sand));
+        game.set_cell(3, 1, Cell { hardness: 5, ..Cell::new(Cells::Sand) });
+        game.set_background(1, 1, Cells::Wall);
+        game.update(&mut crate::game::Frame::new());
+        game
+    }
+
+    fn encode(game: &Game) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        game.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip_keeps_the_world() {
+        let game = sample_game();
+        let loaded = Game::read_from(encode(&game).as_slice()).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (6, 4));
+        assert_eq!(loaded.iteration, 7);
+        assert_eq!(loaded.seed(), 42);
+        assert_eq!(loaded.tick(), 1);
+        assert_eq!(loaded.cells(), game.cells());
+        assert_eq!(loaded.background(), game.background());
+    }
+
+    #[test]
+    fn round_trip_keeps_simulating_the_same() {
+        let (mut game, mut frame) = (sample_game(), crate::game::Frame::new());
+        let mut loaded = Game::read_from(encode(&game).as_slice()).unwrap();
+        for _ in 0..20 {
+            game.update(&mut frame);
+            loaded.update(&mut frame);
+        }
+        assert_eq!(loaded.cells(), game.cells());
+    }
+
+    #[test]
+    fn reads_version_1_without_background() {
+        let mut bytes = Vec::new();
+        codec::write_header(&mut bytes, MAGIC, 1).unwrap();
+        for value in [2, 1, 3] {
+            codec::write_u32(&mut bytes, value).unwrap();
+        }
+        codec::write_u64(&mut bytes, 9).unwrap();
+        codec::write_u64(&mut bytes, 5).unwrap();
+        codec::write_u32(&mut bytes, 1).unwrap();
+        codec::write_cell(&mut bytes, &Cell::new(Cells::Sand)).unwrap();
+        codec::write_u32(&mut bytes, 1).unwrap();
+        codec::write_cell(&mut bytes, &Cell::new(Cells::Wall)).unwrap();
+        let game = Game::read_from(bytes.as_slice()).unwrap();
+        assert_eq!((game.width(), game.height(), game.iteration, game.seed(), game.tick()), (2, 1, 3, 9, 5));
+        assert_eq!(game.cells(), [Cell::new(Cells::Sand), Cell::new(Cells::Wall)]);
+        assert_eq!(game.background(), [Cells::Air, Cells::Air]);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = encode(&sample_game());
+        bytes[0] = b'X';
+        assert!(matches!(Game::read_from(bytes.as_slice()), Err(DecodeError::BadMagic)));
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in [0, VERSION + 1] {
+            let mut bytes = encode(&sample_game());
+            bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&version.to_le_bytes());
+            match Game::read_from(bytes.as_slice()) {
+                Err(DecodeError::UnsupportedVersion(found)) => assert_eq!(found, version),
+                other => panic!("expected unsupported version {}, got {:?}", version, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_runs_overflowing_the_world() {
+        let mut bytes = Vec::new();
+        codec::write_header(&mut bytes, MAGIC, VERSION).unwrap();
+        for value in [2, 2, 1] {
+            codec::write_u32(&mut bytes, value).unwrap();
+        }
+        codec::write_u64(&mut bytes, 0).unwrap();
+        codec::write_u64(&mut bytes, 0).unwrap();
+        codec::write_u32(&mut bytes, 5).unwrap();
+        codec::write_cell(&mut bytes, &Cell::new(Cells::Sand)).unwrap();
+        assert!(matches!(Game::read_from(bytes.as_slice()), Err(DecodeError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let bytes = encode(&sample_game());
+        for length in 0..bytes.len() {
+            match Game::read_from(&bytes[..length]) {
+                Err(DecodeError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+                Err(DecodeError::BadMagic) if length < MAGIC.len() => {}
+                other => panic!("expected truncated input at {} bytes to fail, got {:?}", length, other.map(|_| ())),
+            }
+        }
+    }
+}
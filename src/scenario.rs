@@ -0,0 +1,145 @@
+//! Helpers for testing the simulation rules.
+//!
+//! A scenario is a world written in the text format, run for a number of ticks with a fixed seed
+//! and checked afterwards. Every assertion panics with the current world printed.
+//!
+//! ```
+//! use jiskricka::game::Cells;
+//! use jiskricka::scenario::Scenario;
+//!
+//! Scenario::new(r"
+//!     ..s..
+//!     .....
+//!     .###.
+//! ")
+//! .run(50)
+//! .assert_count_unchanged(Cells::Sand)
+//! .assert_no_floating(Cells::Sand)
+//! .assert_matches(r"
+//!     .....
+//!     ..s..
+//!     .###.
+//! ");
+//! ```
+
+use crate::game::{Cells, Frame, Game};
+use crate::stats::WorldStats;
+use crate::text::Legend;
+
+pub struct Scenario {
+    game: Game,
+    legend: Legend,
+    /// Stats of the world before the first tick.
+    initial: WorldStats,
+}
+
+impl Scenario {
+    /// Seed used unless the scenario picks its own.
+    pub const DEFAULT_SEED: u64 = 0;
+
+    /// Builds the scenario from a world in the default legend, panics if the text isn't valid.
+    pub fn new(text: &str) -> Scenario {
+        Scenario::with_legend(text, Legend::default())
+    }
+
+    pub fn with_legend(text: &str, legend: Legend) -> Scenario {
+        let mut game = Game::from_text(text, &legend).unwrap_or_else(|err| panic!("invalid scenario: {}", err));
+        game.reseed(Self::DEFAULT_SEED);
+        game.check_conservation = true;
+        let initial = game.stats();
+        Scenario { game, legend, initial }
+    }
+
+    pub fn seed(&mut self, seed: u64) -> &mut Scenario {
+        self.game.reseed(seed);
+        self
+    }
+
+    /// Applies the frame to the world, for scenarios that need more than the text can describe.
+    pub fn apply(&mut self, frame: &mut Frame) -> &mut Scenario {
+        self.game.apply_frame(frame);
+        self
+    }
+
+    pub fn run(&mut self, ticks: u64) -> &mut Scenario {
+        let mut frame = Frame::new();
+        for _ in 0..ticks {
+            self.game.update(&mut frame);
+        }
+        self
+    }
+
+    /// Runs until the world is at rest for `stable_ticks` ticks, panics if it takes more than `max_ticks`.
+    pub fn settle(&mut self, stable_ticks: u64, max_ticks: u64) -> &mut Scenario {
+        if !self.game.settle(stable_ticks, max_ticks).converged {
+            self.fail("world didn't settle");
+        }
+        self
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    /// Panics with the message and the current world.
+    fn fail(&self, message: &str) -> ! {
+        panic!(
+            "{} after {} ticks (seed {}):\n{}",
+            message,
+            self.game.tick(),
+            self.game.seed(),
+            self.game.to_text(&self.legend)
+        )
+    }
+
+    pub fn assert_count(&self, kind: Cells, expected: usize) -> &Scenario {
+        let count = self.game.count(kind);
+        if count != expected {
+            self.fail(&format!("expected {} {:?} cells, found {}", expected, kind, count));
+        }
+        self
+    }
+
+    /// Asserts there are as many cells of the material as there were at the start.
+    pub fn assert_count_unchanged(&self, kind: Cells) -> &Scenario {
+        self.assert_count(kind, self.initial.count(kind))
+    }
+
+    /// Asserts every cell of the material rests on the bottom border or on something other than air.
+    pub fn assert_no_floating(&self, kind: Cells) -> &Scenario {
+        for y in 0..self.game.height() as i32 {
+            for x in 0..self.game.width() as i32 {
+                if self.game.get_cell(x, y).kind != kind {
+                    continue;
+                }
+                if let Some(below) = self.game.try_get_cell(x, y + 1) {
+                    if below.kind == Cells::Air {
+                        self.fail(&format!("{:?} at {}, {} is floating", kind, x, y));
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Asserts the world's materials match the expected world, written in the scenario's legend.
+    pub fn assert_matches(&self, expected: &str) -> &Scenario {
+        let expected_game =
+            Game::from_text(expected, &self.legend).unwrap_or_else(|err| panic!("invalid expected world: {}", err));
+        let matches = expected_game.width() == self.game.width()
+            && expected_game.height() == self.game.height()
+            && expected_game
+                .cells()
+                .iter()
+                .zip(self.game.cells())
+                .all(|(expected, actual)| expected.kind == actual.kind);
+        if !matches {
+            self.fail(&format!("world doesn't match, expected:\n{}found", expected_game.to_text(&self.legend)));
+        }
+        self
+    }
+}
@@ -0,0 +1,97 @@
+use crate::game::{Cells, Game};
+
+/// Snapshot of what the world is made of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldStats {
+    /// Number of cells of each material, indexed by `Cells::index`.
+    pub counts: [usize; Cells::ALL.len()],
+    /// Sum of the mass of every cell.
+    pub total_mass: u64,
+    /// Number of cells changed during the last tick.
+    pub changed_last_tick: usize,
+}
+
+impl WorldStats {
+    pub fn count(&self, kind: Cells) -> usize {
+        self.counts[kind.index()]
+    }
+}
+
+impl std::fmt::Display for WorldStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for kind in Cells::ALL {
+            writeln!(f, "{:?}: {}", kind, self.count(kind))?;
+        }
+        writeln!(f, "total mass: {}", self.total_mass)?;
+        writeln!(f, "changed last tick: {}", self.changed_last_tick)
+    }
+}
+
+impl Game {
+    pub fn stats(&self) -> WorldStats {
+        let mut counts = [0; Cells::ALL.len()];
+        for cell in self.cells() {
+            counts[cell.kind.index()] += 1;
+        }
+        let total_mass = Cells::ALL
+            .iter()
+            .map(|kind| kind.mass() as u64 * counts[kind.index()] as u64)
+            .sum();
+        WorldStats {
+            counts,
+            total_mass,
+            changed_last_tick: self.changed_last_tick(),
+        }
+    }
+
+    /// Returns the number of cells of the material.
+    pub fn count(&self, kind: Cells) -> usize {
+        self.cells().iter().filter(|cell| cell.kind == kind).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Cell, Cells, Frame, Game};
+
+    /// World of 3 by 3 cells with a wall floor, a grain of sand in the air and a pool of lava.
+    fn hand_built() -> Game {
+        let mut game = Game::with_seed(3, 3, 0);
+        for x in 0..3 {
+            game.set_cell(x, 2, Cell::new(Cells::Wall));
+        }
+        game.set_cell(1, 0, Cell::new(Cells::Sand));
+        game.set_cell(0, 1, Cell::new(Cells::Lava));
+        game
+    }
+
+    #[test]
+    fn stats_count_the_hand_built_world() {
+        let game = hand_built();
+        let stats = game.stats();
+        assert_eq!(stats.count(Cells::Air), 4);
+        assert_eq!(stats.count(Cells::Wall), 3);
+        assert_eq!(stats.count(Cells::Sand), 1);
+        assert_eq!(stats.count(Cells::Lava), 1);
+        assert_eq!(stats.total_mass, 5);
+        for kind in Cells::ALL {
+            assert_eq!(stats.count(kind), game.count(kind));
+        }
+    }
+
+    #[test]
+    fn changed_last_tick_follows_the_updates() {
+        let mut game = hand_built();
+        assert_eq!(game.stats().changed_last_tick, 0);
+        // the five placed cells count towards the first tick, the sand falling adds the cell below it
+        game.update(&mut Frame::new());
+        assert_eq!(game.get_cell(1, 1).kind, Cells::Sand);
+        assert_eq!(game.stats().changed_last_tick, 6);
+        game.update(&mut Frame::new());
+        assert_eq!(game.stats().changed_last_tick, 0);
+        game.set_cell(2, 1, Cell::new(Cells::Sand));
+        game.update(&mut Frame::new());
+        assert_eq!(game.stats().changed_last_tick, 1);
+        assert_eq!(game.stats().total_mass, 6);
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt::Write;
+
+use crate::camera::Area;
+use crate::color::Color;
+use crate::game::{Coords, Game};
+use crate::overlay::Overlays;
+use crate::render::Renderer;
+
+/// Upper half block, the foreground colour paints the upper cell and the background colour the lower one.
+const HALF_BLOCK: char = '▀';
+
+/// Draws the world as ANSI true colour text, two cells above each other per character.
+///
+/// Cells can still be drawn after [`Game::draw`], to paint markers like a cursor over the world.
+#[derive(Debug, Clone, Default)]
+pub struct TerminalRenderer {
+    pub overlays: Overlays,
+    area: Area,
+    /// Colours of the drawn area, row by row.
+    colors: Vec<Color>,
+}
+
+impl TerminalRenderer {
+    pub fn new() -> TerminalRenderer {
+        TerminalRenderer::default()
+    }
+
+    /// Returns the number of characters per line of the output.
+    pub fn columns(&self) -> u32 {
+        self.area.width
+    }
+
+    /// Returns the number of lines of the output.
+    pub fn lines(&self) -> u32 {
+        self.area.height.div_ceil(2)
+    }
+
+    fn color(&self, x: u32, y: u32) -> Option<Color> {
+        (y < self.area.height).then(|| self.colors[(y * self.area.width + x) as usize])
+    }
+
+    /// Returns the drawn area as escape sequences, every line ending with `line_end`.
+    ///
+    /// Raw mode terminals need `"\r\n"`, anything else `"\n"`.
+    pub fn to_ansi(&self, line_end: &str) -> String {
+        let mut out = String::new();
+        for line in 0..self.lines() {
+            let mut last = None;
+            for x in 0..self.area.width {
+                let upper = self.color(x, line * 2);
+                let lower = self.color(x, line * 2 + 1);
+                if last != Some((upper, lower)) {
+                    if let Some(upper) = upper {
+                        let _ = write!(out, "\x1b[38;2;{};{};{}m", upper.r, upper.g, upper.b);
+                    }
+                    match lower {
+                        Some(lower) => {
+                            let _ = write!(out, "\x1b[48;2;{};{};{}m", lower.r, lower.g, lower.b);
+                        }
+                        None => out.push_str("\x1b[49m"),
+                    }
+                    last = Some((upper, lower));
+                }
+                out.push(HALF_BLOCK);
+            }
+            out.push_str("\x1b[0m");
+            out.push_str(line_end);
+        }
+        out
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn begin(&mut self, _game: &Game, area: Area) {
+        self.area = area;
+        self.colors.clear();
+        self.colors.resize((area.width * area.height) as usize, Color::BLACK);
+    }
+
+    fn draw_cell(&mut self, x: i32, y: i32, color: Color) {
+        if self.area.contains(Coords { x, y }) {
+            let index = (y - self.area.y) as u32 * self.area.width + (x - self.area.x) as u32;
+            self.colors[index as usize] = color;
+        }
+    }
+
+    fn overlays(&self) -> Overlays {
+        self.overlays
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_ansi_pairs_rows_into_half_blocks() {
+        let (red, blue, green, white) =
+            (Color::rgb(255, 0, 0), Color::rgb(0, 0, 255), Color::rgb(0, 255, 0), Color::rgb(255, 255, 255));
+        let mut renderer = TerminalRenderer::new();
+        renderer.begin(&Game::with_seed(2, 3, 0), Area { x: 0, y: 0, width: 2, height: 3 });
+        for (x, y, color) in [(0, 0, red), (1, 0, red), (0, 1, blue), (1, 1, blue), (0, 2, green), (1, 2, white)] {
+            renderer.draw_cell(x, y, color);
+        }
+        assert_eq!((renderer.columns(), renderer.lines()), (2, 2));
+        assert_eq!(
+            renderer.to_ansi("\n"),
+            concat!(
+                // the colours are only written again when they change
+                "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀▀\x1b[0m\n",
+                // the odd last row has no lower cell, the terminal's own background shows
+                "\x1b[38;2;0;255;0m\x1b[49m▀\x1b[38;2;255;255;255m\x1b[49m▀\x1b[0m\n",
+            )
+        );
+    }
+
+    #[test]
+    fn to_ansi_ends_lines_as_asked_and_skips_what_is_outside_the_area() {
+        let mut renderer = TerminalRenderer::new();
+        renderer.begin(&Game::with_seed(4, 4, 0), Area { x: 1, y: 1, width: 1, height: 2 });
+        renderer.draw_cell(0, 0, Color::rgb(1, 2, 3));
+        renderer.draw_cell(1, 2, Color::rgb(4, 5, 6));
+        assert_eq!(renderer.to_ansi("\r\n"), "\x1b[38;2;0;0;0m\x1b[48;2;4;5;6m▀\x1b[0m\r\n");
+        renderer.begin(&Game::with_seed(0, 0, 0), Area::default());
+        assert_eq!(renderer.to_ansi("\n"), "");
+    }
+}
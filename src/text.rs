@@ -0,0 +1,187 @@
+use crate::game::{Cell, Cells, Game};
+
+/// Characters used for each material in the text format.
+#[derive(Debug, Clone)]
+pub struct Legend {
+    entries: Vec<(char, Cells)>,
+}
+
+impl Legend {
+    /// Creates an empty legend.
+    pub fn new() -> Legend {
+        Legend { entries: Vec::new() }
+    }
+
+    /// Maps the character to the material, replacing previous mappings of either.
+    pub fn with(mut self, ch: char, kind: Cells) -> Legend {
+        self.entries.retain(|(entry_ch, entry_kind)| *entry_ch != ch && *entry_kind != kind);
+        self.entries.push((ch, kind));
+        self
+    }
+
+    pub fn char_for(&self, kind: Cells) -> Option<char> {
+        self.entries.iter().find(|(_, entry)| *entry == kind).map(|(ch, _)| *ch)
+    }
+
+    pub fn kind_for(&self, ch: char) -> Option<Cells> {
+        self.entries.iter().find(|(entry, _)| *entry == ch).map(|(_, kind)| *kind)
+    }
+}
+
+impl Default for Legend {
+    fn default() -> Legend {
+        Legend::new()
+            .with('.', Cells::Air)
+            .with('#', Cells::Wall)
+            .with('s', Cells::Sand)
+            .with('l', Cells::Lava)
+    }
+}
+
+/// Error returned when a text world can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseGameError {
+    /// The text contains no rows.
+    Empty,
+    /// A row is of a different length than the first one.
+    RaggedRow { row: u32, expected: u32, found: u32 },
+    /// The character isn't in the legend.
+    UnknownChar { x: u32, y: u32, ch: char },
+}
+
+impl std::fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseGameError::Empty => write!(f, "world has no rows"),
+            ParseGameError::RaggedRow { row, expected, found } => {
+                write!(f, "row {} is {} cells wide, expected {}", row, found, expected)
+            }
+            ParseGameError::UnknownChar { x, y, ch } => write!(f, "unknown character {:?} at {}, {}", ch, x, y),
+        }
+    }
+}
+
+impl std::error::Error for ParseGameError {}
+
+impl Game {
+    /// Writes the world one character per cell, materials missing from the legend are written as `?`.
+    pub fn to_text(&self, legend: &Legend) -> String {
+        let mut text = String::with_capacity(((self.width() + 1) * self.height()) as usize);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                text.push(legend.char_for(self.get_cell(x as i32, y as i32).kind).unwrap_or('?'));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses a world written one character per cell.
+    ///
+    /// Unless the legend uses a space, blank lines are skipped and rows are trimmed,
+    /// so worlds can be written indented inside raw string literals.
+    /// With a space in the legend, rows of spaces are cells too and only empty lines at the start and end are skipped.
+    pub fn from_text(text: &str, legend: &Legend) -> Result<Game, ParseGameError> {
+        let lines: Vec<&str> = if legend.kind_for(' ').is_none() {
+            text.lines().map(str::trim).filter(|line| !line.is_empty()).collect()
+        } else {
+            let lines: Vec<&str> = text.lines().map(|line| line.trim_end_matches('\r')).collect();
+            let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+            let last = lines.iter().rposition(|line| !line.is_empty()).map_or(first, |last| last + 1);
+            lines[first..last].to_vec()
+        };
+        let rows: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect()).collect();
+        let width = match rows.first() {
+            Some(row) => row.len() as u32,
+            None => return Err(ParseGameError::Empty),
+        };
+        let mut game = Game::new(width, rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() as u32 != width {
+                return Err(ParseGameError::RaggedRow { row: y as u32, expected: width, found: row.len() as u32 });
+            }
+            for (x, ch) in row.iter().enumerate() {
+                let kind = legend
+                    .kind_for(*ch)
+                    .ok_or(ParseGameError::UnknownChar { x: x as u32, y: y as u32, ch: *ch })?;
+                game.set_cell(x as i32, y as i32, Cell::new(kind));
+            }
+        }
+        Ok(game)
+    }
+}
+
+impl std::fmt::Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_text(&Legend::default()))
+    }
+}
+
+impl std::str::FromStr for Game {
+    type Err = ParseGameError;
+
+    fn from_str(s: &str) -> Result<Game, ParseGameError> {
+        Game::from_text(s, &Legend::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space_legend() -> Legend {
+        Legend::new().with(' ', Cells::Air).with('#', Cells::Wall).with('*', Cells::Sand)
+    }
+
+    /// A 3x3 world whose first and last rows are air.
+    fn hollow_game() -> Game {
+        let mut game = Game::with_seed(3, 3, 0);
+        game.set_cell(0, 1, Cell::new(Cells::Wall));
+        game.set_cell(2, 1, Cell::new(Cells::Sand));
+        game
+    }
+
+    fn assert_round_trip(game: &Game, legend: &Legend) {
+        let text = game.to_text(legend);
+        let parsed = Game::from_text(&text, legend).unwrap_or_else(|err| panic!("failed to parse {:?}: {}", text, err));
+        assert_eq!((parsed.width(), parsed.height()), (game.width(), game.height()), "size of {:?}", text);
+        assert_eq!(parsed.cells(), game.cells(), "cells of {:?}", text);
+    }
+
+    #[test]
+    fn round_trip_with_default_legend() {
+        assert_round_trip(&hollow_game(), &Legend::default());
+        assert_round_trip(&Game::with_seed(4, 2, 0), &Legend::default());
+    }
+
+    #[test]
+    fn round_trip_with_space_legend() {
+        assert_eq!(hollow_game().to_text(&space_legend()), "   \n# *\n   \n");
+        assert_round_trip(&hollow_game(), &space_legend());
+        assert_round_trip(&Game::with_seed(4, 2, 0), &space_legend());
+    }
+
+    #[test]
+    fn space_legend_skips_only_surrounding_empty_lines() {
+        let game = Game::from_text("\n   \n# *\n   \n\n", &space_legend()).unwrap();
+        assert_eq!((game.width(), game.height()), (3, 3));
+        assert_eq!(
+            Game::from_text("# *\n\n# *\n", &space_legend()).err(),
+            Some(ParseGameError::RaggedRow { row: 1, expected: 3, found: 0 })
+        );
+    }
+
+    #[test]
+    fn default_legend_trims_indented_text() {
+        let game = Game::from_text(
+            r"
+            ...
+            #.s
+        ",
+            &Legend::default(),
+        )
+        .unwrap();
+        assert_eq!((game.width(), game.height()), (3, 2));
+        assert_eq!(game.get_cell(2, 1).kind, Cells::Sand);
+    }
+}
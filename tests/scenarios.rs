@@ -0,0 +1,247 @@
+use jiskricka::force::{ForceField, Region};
+use jiskricka::game::{Action, CellAction, Cells, Coords, Frame};
+use jiskricka::scenario::Scenario;
+
+#[test]
+fn sand_falls_onto_a_wall() {
+    Scenario::new(
+        r"
+        ..s..
+        .....
+        .....
+        .###.
+    ",
+    )
+    .run(50)
+    .assert_matches(
+        r"
+        .....
+        .....
+        ..s..
+        .###.
+    ",
+    );
+}
+
+#[test]
+fn sand_falls_to_the_bottom_border() {
+    Scenario::new(
+        r"
+        s...
+        ..s.
+        ....
+        ....
+    ",
+    )
+    .run(50)
+    .assert_matches(
+        r"
+        ....
+        ....
+        ....
+        s.s.
+    ",
+    );
+}
+
+#[test]
+fn sand_stacks_on_sand() {
+    Scenario::new(
+        r"
+        .s.
+        ...
+        .s.
+        ...
+        ###
+    ",
+    )
+    .run(50)
+    .assert_matches(
+        r"
+        ...
+        ...
+        .s.
+        .s.
+        ###
+    ",
+    );
+}
+
+#[test]
+fn sand_count_is_unchanged_after_many_ticks() {
+    for seed in 0..4 {
+        Scenario::new(
+            r"
+            s.s.ss.s
+            .s..s...
+            ..ss..s.
+            ........
+            ..####..
+            ........
+        ",
+        )
+        .seed(seed)
+        .run(50)
+        .assert_count_unchanged(Cells::Sand)
+        .assert_count_unchanged(Cells::Wall);
+    }
+}
+
+#[test]
+fn no_sand_floats_once_it_landed() {
+    for seed in 0..4 {
+        Scenario::new(
+            r"
+            ssssssss
+            ........
+            ...ss...
+            ........
+            .##..##.
+            ........
+        ",
+        )
+        .seed(seed)
+        .run(50)
+        .assert_no_floating(Cells::Sand)
+        .assert_count_unchanged(Cells::Sand);
+    }
+}
+
+#[test]
+fn sand_poured_by_a_frame_falls_too() {
+    let mut frame = Frame::new();
+    frame.add_action(Action::Cell(Coords { x: 1, y: 0 }, CellAction::SetKind(Cells::Sand)));
+    Scenario::new(
+        r"
+        ...
+        ...
+        ###
+    ",
+    )
+    .apply(&mut frame)
+    .run(50)
+    .assert_count(Cells::Sand, 1)
+    .assert_matches(
+        r"
+        ...
+        .s.
+        ###
+    ",
+    );
+}
+
+#[test]
+fn a_single_falling_grain_keeps_the_world_from_settling() {
+    // every tick only updates a tenth of the cells, so the grain sits still in most ticks while falling
+    let mut rows = vec![".".repeat(100); 100];
+    rows[0].replace_range(50..51, "s");
+    let mut scenario = Scenario::new(&rows.join("\n"));
+    scenario.settle(3, 10_000).assert_no_floating(Cells::Sand);
+    assert_eq!(scenario.game().get_cell(50, 99).kind, Cells::Sand);
+}
+
+#[test]
+fn lava_spreads_over_the_floor() {
+    Scenario::new(
+        r"
+        l...
+        l...
+        l...
+        l...
+        ####
+    ",
+    )
+    .settle(3, 1_000)
+    .assert_count_unchanged(Cells::Lava)
+    .assert_matches(
+        r"
+        ....
+        ....
+        ....
+        llll
+        ####
+    ",
+    );
+}
+
+#[test]
+fn sand_sinks_through_lava() {
+    for seed in 0..4 {
+        Scenario::new(
+            r"
+            .s.
+            .l.
+            ###
+        ",
+        )
+        .seed(seed)
+        .settle(3, 1_000)
+        .assert_count_unchanged(Cells::Lava)
+        .assert_count_unchanged(Cells::Sand)
+        .assert_no_floating(Cells::Lava)
+        .assert_no_floating(Cells::Sand);
+    }
+}
+
+#[test]
+fn narrow_puddle_of_lava_comes_to_rest() {
+    for seed in 0..8 {
+        Scenario::new(
+            r"
+            l...
+            ####
+        ",
+        )
+        .seed(seed)
+        .settle(3, 2_000)
+        .assert_matches(
+            r"
+            l...
+            ####
+        ",
+        );
+    }
+}
+
+#[test]
+fn lava_levels_out_in_a_basin() {
+    for seed in 0..4 {
+        let mut scenario = Scenario::new(
+            r"
+            ........
+            ..ll....
+            ..l.....
+            ########
+        ",
+        );
+        scenario.seed(seed).settle(3, 2_000).assert_count_unchanged(Cells::Lava).assert_no_floating(Cells::Lava);
+        let game = scenario.game();
+        let surface = (0..8).filter(|x| game.get_cell(*x, 2).kind == Cells::Lava).count();
+        assert_eq!(surface, 3, "lava isn't flat with seed {}:\n{}", seed, game);
+    }
+}
+
+#[test]
+fn lava_that_can_flow_to_one_side_only_is_not_at_rest() {
+    for world in ["l.\n#.", ".l\n.#"] {
+        for seed in 0..64 {
+            let mut scenario = Scenario::new(world);
+            scenario.seed(seed);
+            assert!(!scenario.game().is_at_rest(), "{:?} counted as at rest with seed {}", world, seed);
+        }
+    }
+    assert!(Scenario::new("l.\n##").game().is_at_rest());
+}
+
+#[test]
+fn rare_turbulent_pushes_keep_the_world_from_resting() {
+    // pushed straight down into the wall, only the turbulence can push the grain diagonally
+    let field = |turbulence| {
+        ForceField::new(Region::Rect { x: 0, y: 0, width: 2, height: 2 }, (0.0, 1.0), 0.01).with_turbulence(turbulence)
+    };
+    let mut scenario = Scenario::new("s.\n#.");
+    scenario.game_mut().force_fields.push(field(0.0));
+    assert!(scenario.game().is_at_rest());
+    scenario.game_mut().force_fields[0] = field(0.6);
+    assert!(!scenario.game().is_at_rest());
+}